@@ -13,6 +13,7 @@ pub mod keygen;
 pub mod public_key_store;
 pub mod secret_key_store;
 mod signer;
+pub mod store_retry;
 pub mod threshold;
 pub mod tls;
 pub mod types;
@@ -29,6 +30,7 @@ use crate::api::{
     CspTlsHandshakeSignerProvider, NiDkgCspClient, ThresholdSignatureCspClient,
 };
 use crate::secret_key_store::SecretKeyStore;
+use crate::store_retry::StoreRetryPolicy;
 use crate::types::{CspPublicKey, ExternalPublicKeys};
 use crate::vault::api::{
     CspPublicKeyStoreError, CspVault, PksAndSksContainsErrors, ValidatePksAndSksError,
@@ -40,6 +42,8 @@ use ic_logger::{info, new_logger, replica_logger::no_op_logger, ReplicaLogger};
 use ic_types::crypto::CurrentNodePublicKeys;
 use key_id::KeyId;
 use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use rand::rngs::OsRng;
+use rand::{CryptoRng, Rng};
 use std::path::Path;
 use std::sync::Arc;
 use std::time::Instant;
@@ -161,9 +165,37 @@ impl Csp {
         tokio_runtime_handle: Option<tokio::runtime::Handle>,
         logger: Option<ReplicaLogger>,
         metrics: Arc<CryptoMetrics>,
+    ) -> Self {
+        Self::new_with_store_retry_policy(
+            config,
+            tokio_runtime_handle,
+            logger,
+            metrics,
+            StoreRetryPolicy::no_retries(),
+        )
+    }
+
+    /// Like [`Self::new`], but if the `config`'s vault type is `InReplica`, the
+    /// vault's key stores retry failed writes to disk according to
+    /// `store_retry_policy`.
+    ///
+    /// The `store_retry_policy` is ignored if the `config`'s vault type is
+    /// `UnixSocket`, since the key stores are then managed by the remote vault.
+    ///
+    /// # Panics
+    /// Panics if the `config`'s vault type is `UnixSocket` and
+    /// `tokio_runtime_handle` is `None`.
+    pub fn new_with_store_retry_policy(
+        config: &CryptoConfig,
+        tokio_runtime_handle: Option<tokio::runtime::Handle>,
+        logger: Option<ReplicaLogger>,
+        metrics: Arc<CryptoMetrics>,
+        store_retry_policy: StoreRetryPolicy,
     ) -> Self {
         match &config.csp_vault_type {
-            CspVaultType::InReplica => Self::new_with_in_replica_vault(config, logger, metrics),
+            CspVaultType::InReplica => {
+                Self::new_with_in_replica_vault(config, OsRng, logger, metrics, store_retry_policy)
+            }
             CspVaultType::UnixSocket(socket_path) => Self::new_with_unix_socket_vault(
                 socket_path,
                 tokio_runtime_handle.expect("missing tokio runtime handle"),
@@ -174,18 +206,49 @@ impl Csp {
         }
     }
 
-    fn new_with_in_replica_vault(
+    /// Like [`Self::new_with_store_retry_policy`], but the in-replica vault uses
+    /// `csprng` instead of the operating system as source of randomness, e.g., to
+    /// generate reproducible node keys for test deployments.
+    ///
+    /// # Panics
+    /// Panics if the `config`'s vault type is not `InReplica`.
+    pub fn new_with_in_replica_vault_and_rng<R>(
         config: &CryptoConfig,
+        csprng: R,
         logger: Option<ReplicaLogger>,
         metrics: Arc<CryptoMetrics>,
-    ) -> Self {
+        store_retry_policy: StoreRetryPolicy,
+    ) -> Self
+    where
+        R: Rng + CryptoRng + Send + Sync + 'static,
+    {
+        assert_eq!(
+            config.csp_vault_type,
+            CspVaultType::InReplica,
+            "a CSP with a custom RNG requires an in-replica vault"
+        );
+        Self::new_with_in_replica_vault(config, csprng, logger, metrics, store_retry_policy)
+    }
+
+    fn new_with_in_replica_vault<R>(
+        config: &CryptoConfig,
+        csprng: R,
+        logger: Option<ReplicaLogger>,
+        metrics: Arc<CryptoMetrics>,
+        store_retry_policy: StoreRetryPolicy,
+    ) -> Self
+    where
+        R: Rng + CryptoRng + Send + Sync + 'static,
+    {
         let logger = logger.unwrap_or_else(no_op_logger);
         info!(
             logger,
             "Proceeding with an in-replica csp_vault, CryptoConfig: {:?}", config
         );
-        let csp_vault = Arc::new(LocalCspVault::new_in_dir(
+        let csp_vault = Arc::new(LocalCspVault::new_in_dir_with_rng(
             &config.crypto_root,
+            csprng,
+            store_retry_policy,
             metrics.clone(),
             new_logger!(&logger),
         ));
@@ -2,6 +2,7 @@ use crate::public_key_store::PublicKeyGenerationTimestamps;
 use crate::public_key_store::{
     PublicKeyAddError, PublicKeyRetainError, PublicKeySetOnceError, PublicKeyStore,
 };
+use crate::store_retry::{write_with_retries, StoreRetryPolicy};
 use ic_logger::{debug, ReplicaLogger};
 use ic_protobuf::crypto::v1::NodePublicKeys;
 use ic_protobuf::registry::crypto::v1::{PublicKey as PublicKeyProto, X509PublicKeyCert};
@@ -20,6 +21,7 @@ const CURRENT_PKS_VERSION: u32 = 1;
 pub struct ProtoPublicKeyStore {
     proto_file: PathBuf,
     keys: NodePublicKeys,
+    write_retry_policy: StoreRetryPolicy,
     logger: ReplicaLogger,
 }
 
@@ -40,15 +42,43 @@ impl ProtoPublicKeyStore {
         ProtoPublicKeyStore {
             proto_file,
             keys,
+            write_retry_policy: StoreRetryPolicy::no_retries(),
             logger,
         }
     }
 
+    /// Retries failed writes of the store to disk according to `write_retry_policy`.
+    ///
+    /// By default, failed writes are not retried.
+    pub fn with_write_retry_policy(mut self, write_retry_policy: StoreRetryPolicy) -> Self {
+        self.write_retry_policy = write_retry_policy;
+        self
+    }
+
     /// Returns the path to the protobuf file storing the keys.
     pub fn proto_file_path(&self) -> &Path {
         self.proto_file.as_path()
     }
 
+    /// Removes the TLS certificate from the store and returns it, or returns `None`
+    /// if the store does not contain a TLS certificate.
+    ///
+    /// In contrast to the other node keys, which are only ever set once, this allows
+    /// replacing a TLS certificate that is used as standalone TLS identity of a
+    /// service other than the replica. The other keys of the store are not modified.
+    ///
+    /// Returns an error if the updated store could not be written to disk.
+    pub fn remove_tls_certificate(&mut self) -> Result<Option<X509PublicKeyCert>, io::Error> {
+        let tls_certificate = self.keys.tls_certificate.take();
+        if tls_certificate.is_some() {
+            if let Err(error) = self.write_node_public_keys_proto_to_disk() {
+                self.keys.tls_certificate = tls_certificate;
+                return Err(error);
+            }
+        }
+        Ok(tls_certificate)
+    }
+
     fn read_node_public_keys_proto_from_disk(path: &Path) -> Option<NodePublicKeys> {
         match fs::read(path) {
             Ok(data) => {
@@ -64,7 +94,15 @@ impl ProtoPublicKeyStore {
     fn write_node_public_keys_proto_to_disk(&mut self) -> Result<(), io::Error> {
         // Setting the version to CURRENT_PKS_VERSION to unify all stores in production.
         self.keys.version = CURRENT_PKS_VERSION;
-        ic_utils::fs::write_protobuf_using_tmp_file(&self.proto_file, &self.keys)
+        // Retrying is safe, since each attempt writes the complete store to a temporary file,
+        // which replaces a leftover one of a failed attempt, before renaming it.
+        write_with_retries(
+            &self.write_retry_policy,
+            &self.logger,
+            &self.proto_file,
+            || ic_utils::fs::write_protobuf_using_tmp_file(&self.proto_file, &self.keys),
+            || {},
+        )
     }
 }
 
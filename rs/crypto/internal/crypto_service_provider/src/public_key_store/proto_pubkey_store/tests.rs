@@ -340,6 +340,56 @@ fn should_deserialize_existing_public_key_store() {
     assert_eq!(store.tls_certificate(), Some(valid_tls_certificate()));
 }
 
+#[test]
+fn should_remove_only_tls_certificate() {
+    let temp_dir = temp_dir();
+    let mut store = public_key_store(&temp_dir);
+    store
+        .set_once_node_signing_pubkey(valid_node_signing_public_key())
+        .expect("cannot set public key");
+    store
+        .set_once_tls_certificate(valid_tls_certificate())
+        .expect("cannot set TLS certificate");
+
+    assert_matches!(
+        store.remove_tls_certificate(),
+        Ok(Some(cert)) if cert == valid_tls_certificate()
+    );
+
+    let reopened_store = public_key_store(&temp_dir);
+    assert_eq!(reopened_store.tls_certificate(), None);
+    assert_eq!(
+        reopened_store.node_signing_pubkey(),
+        Some(valid_node_signing_public_key())
+    );
+}
+
+#[test]
+fn should_allow_setting_tls_certificate_again_after_removing_it() {
+    let temp_dir = temp_dir();
+    let mut store = public_key_store(&temp_dir);
+    store
+        .set_once_tls_certificate(valid_tls_certificate())
+        .expect("cannot set TLS certificate");
+    store
+        .remove_tls_certificate()
+        .expect("cannot remove TLS certificate");
+
+    assert_matches!(
+        store.set_once_tls_certificate(valid_tls_certificate()),
+        Ok(())
+    );
+}
+
+#[test]
+fn should_return_none_when_removing_non_existing_tls_certificate() {
+    let temp_dir = temp_dir();
+    let mut store = public_key_store(&temp_dir);
+
+    assert_matches!(store.remove_tls_certificate(), Ok(None));
+    assert!(!temp_dir.path().join(PUBLIC_KEYS_FILE).exists());
+}
+
 fn equal_ignoring_timestamp(left: &Vec<PublicKey>, right: &Vec<PublicKey>) -> bool {
     left.len() == right.len()
         && left
@@ -5,6 +5,7 @@ use crate::key_id::KeyId;
 use crate::secret_key_store::{
     Scope, SecretKeyStore, SecretKeyStoreInsertionError, SecretKeyStoreWriteError,
 };
+use crate::store_retry::{write_with_retries, StoreRetryPolicy};
 use crate::types::CspSecretKey;
 use hex::{FromHex, ToHex};
 use ic_config::crypto::CryptoConfig;
@@ -44,6 +45,7 @@ pub struct ProtoSecretKeyStore {
     proto_file: PathBuf,
     old_proto_file_to_zeroize: PathBuf,
     keys: Arc<RwLock<SecretKeys>>,
+    write_retry_policy: StoreRetryPolicy,
     logger: ReplicaLogger,
 }
 
@@ -84,17 +86,47 @@ impl ProtoSecretKeyStore {
             proto_file,
             old_proto_file_to_zeroize,
             keys: Arc::new(RwLock::new(secret_keys)),
+            write_retry_policy: StoreRetryPolicy::no_retries(),
             logger,
         };
         sks.clean_up_old_sks();
         sks
     }
 
+    /// Retries failed writes of the store to disk according to `write_retry_policy`.
+    ///
+    /// By default, failed writes are not retried.
+    pub fn with_write_retry_policy(mut self, write_retry_policy: StoreRetryPolicy) -> Self {
+        self.write_retry_policy = write_retry_policy;
+        self
+    }
+
     /// Returns the path to the protobuf file storing the keys.
     pub fn proto_file_path(&self) -> &Path {
         self.proto_file.as_path()
     }
 
+    /// Removes all keys, regardless of their scope, from the store and returns the
+    /// number of removed keys.
+    ///
+    /// As for any other update of the store, the previous secret key store file is
+    /// overwritten with zeroes before being deleted.
+    ///
+    /// Returns an error if the updated secret key store could not be written.
+    pub fn remove_all(&mut self) -> Result<usize, SecretKeyStoreWriteError> {
+        with_write_lock(&self.keys, |keys| {
+            let removed_keys_count = keys.len();
+            if removed_keys_count > 0 {
+                for key_id in keys.keys() {
+                    info!(self.logger, "Deleting key with ID {}", key_id);
+                }
+                keys.clear();
+                self.write_secret_keys_to_disk(keys)?;
+            }
+            Ok(removed_keys_count)
+        })
+    }
+
     fn clean_up_old_sks(&self) {
         match self.old_proto_file_to_zeroize.try_exists() {
             Ok(exists) => {
@@ -208,7 +240,17 @@ impl ProtoSecretKeyStore {
         }
         // Write the new keystore to a new file and atomically replace the existing keystore.
         // The previously created hard link still points to the old keystore file.
-        ic_utils::fs::write_protobuf_using_tmp_file(&self.proto_file, &sks_proto).map_err(|e| {
+        // Retrying is safe, since each attempt writes the complete keystore. The temporary
+        // file of a failed attempt is zeroized, since it may contain secret keys.
+        let tmp_proto_file = ic_utils::fs::get_tmp_for_path(&self.proto_file);
+        write_with_retries(
+            &self.write_retry_policy,
+            &self.logger,
+            &self.proto_file,
+            || ic_utils::fs::write_protobuf_using_tmp_file(&self.proto_file, &sks_proto),
+            || overwrite_file_with_zeroes_and_delete_if_it_exists(&tmp_proto_file, &self.logger),
+        )
+        .map_err(|e| {
             SecretKeyStoreWriteError::TransientError(format!(
                 "Secret key store internal error writing protobuf using tmp file: {}",
                 e
@@ -293,6 +293,42 @@ fn should_retain_expected_keys() {
     );
 }
 
+#[test]
+fn should_remove_all_keys() {
+    let temp_dir = mk_temp_dir_with_permissions(0o700);
+    let mut key_store = ProtoSecretKeyStore::open(temp_dir.path(), "sks_data.pb", None);
+    let key_ids = [make_key_id(1), make_key_id(2), make_key_id(3)];
+    key_store
+        .insert(key_ids[0], make_secret_key(4), None)
+        .unwrap();
+    key_store
+        .insert(
+            key_ids[1],
+            make_secret_key(5),
+            Some(Scope::Const(ConstScope::Test0)),
+        )
+        .unwrap();
+    key_store
+        .insert(key_ids[2], make_secret_key(6), Some(IDKG_MEGA_SCOPE))
+        .unwrap();
+
+    assert_eq!(key_store.remove_all().unwrap(), 3);
+
+    let reopened_key_store = ProtoSecretKeyStore::open(temp_dir.path(), "sks_data.pb", None);
+    for key_id in &key_ids {
+        assert!(!key_store.contains(key_id));
+        assert!(!reopened_key_store.contains(key_id));
+    }
+}
+
+#[test]
+fn should_remove_no_keys_from_empty_store() {
+    let temp_dir = mk_temp_dir_with_permissions(0o700);
+    let mut key_store = ProtoSecretKeyStore::open(temp_dir.path(), "sks_data.pb", None);
+
+    assert_eq!(key_store.remove_all().unwrap(), 0);
+}
+
 #[test]
 fn should_deserialize_all_existing_secret_key_stores() {
     for version in SecretKeyStoreVersion::all_versions() {
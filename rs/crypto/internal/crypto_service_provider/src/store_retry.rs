@@ -0,0 +1,93 @@
+//! Retrying of failed key store writes.
+use ic_logger::{warn, ReplicaLogger};
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+#[cfg(test)]
+mod tests;
+
+/// Policy for retrying a failed write of a key store file to disk, e.g., because
+/// of a slow or networked file system.
+///
+/// After a failed attempt, the next attempt is made after a backoff that starts at
+/// `initial_backoff` and doubles after each attempt, but never exceeds `max_backoff`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StoreRetryPolicy {
+    /// Maximum number of attempts, including the first one. A value of 0 is treated as 1.
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl StoreRetryPolicy {
+    /// A policy that makes a single attempt, i.e., that does not retry.
+    pub fn no_retries() -> Self {
+        StoreRetryPolicy {
+            max_attempts: 1,
+            initial_backoff: Duration::ZERO,
+            max_backoff: Duration::ZERO,
+        }
+    }
+}
+
+impl Default for StoreRetryPolicy {
+    fn default() -> Self {
+        StoreRetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Calls `write` until it succeeds or `policy.max_attempts` attempts have been made,
+/// and returns the error of the last attempt if all attempts failed.
+///
+/// `write` must write the complete content of `store_file`, so that an attempt that
+/// fails after having partially modified the file system is undone by the next one.
+/// `clean_up` is called after each failed attempt, e.g., to remove temporary files
+/// containing secret keys.
+pub(crate) fn write_with_retries<W, C>(
+    policy: &StoreRetryPolicy,
+    logger: &ReplicaLogger,
+    store_file: &Path,
+    mut write: W,
+    mut clean_up: C,
+) -> io::Result<()>
+where
+    W: FnMut() -> io::Result<()>,
+    C: FnMut(),
+{
+    let max_attempts = policy.max_attempts.max(1);
+    let mut backoff = policy.initial_backoff;
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        match write() {
+            Ok(()) => return Ok(()),
+            Err(error) => {
+                clean_up();
+                warn!(
+                    logger,
+                    "Attempt {} of {} to write key store {} failed: {}",
+                    attempts,
+                    max_attempts,
+                    store_file.to_string_lossy(),
+                    error
+                );
+                if attempts >= max_attempts {
+                    return Err(error);
+                }
+                std::thread::sleep(backoff);
+                backoff = next_backoff(backoff, policy.max_backoff);
+            }
+        }
+    }
+}
+
+// Doubles the `backoff` without exceeding `max_backoff`, saturating instead of
+// overflowing for very large backoffs.
+fn next_backoff(backoff: Duration, max_backoff: Duration) -> Duration {
+    std::cmp::min(backoff.saturating_mul(2), max_backoff)
+}
@@ -0,0 +1,288 @@
+use super::*;
+use crate::public_key_store::proto_pubkey_store::ProtoPublicKeyStore;
+use crate::public_key_store::{PublicKeySetOnceError, PublicKeyStore};
+use crate::secret_key_store::proto_store::ProtoSecretKeyStore;
+use crate::secret_key_store::test_utils::{make_key_id, make_secret_key};
+use crate::secret_key_store::{SecretKeyStore, SecretKeyStoreInsertionError};
+use assert_matches::assert_matches;
+use ic_crypto_internal_csp_test_utils::files::mk_temp_dir_with_permissions;
+use ic_crypto_test_utils_keys::public_keys::valid_node_signing_public_key;
+use ic_logger::replica_logger::{no_op_logger, LogEntryLogger};
+use ic_test_utilities_in_memory_logger::assertions::LogEntriesAssert;
+use ic_test_utilities_in_memory_logger::InMemoryReplicaLogger;
+use slog::Level;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+const STORE_FILE: &str = "/crypto/public_keys.pb";
+
+#[test]
+fn should_retry_until_write_succeeds() {
+    let in_memory_logger = InMemoryReplicaLogger::new();
+    let mut write_calls = 0;
+    let mut clean_up_calls = 0;
+
+    let result = write_with_retries(
+        &policy_with_max_attempts(3),
+        &ReplicaLogger::from(&in_memory_logger),
+        Path::new(STORE_FILE),
+        || {
+            write_calls += 1;
+            if write_calls <= 2 {
+                Err(io::Error::new(io::ErrorKind::Other, "disk busy"))
+            } else {
+                Ok(())
+            }
+        },
+        || clean_up_calls += 1,
+    );
+
+    assert_matches!(result, Ok(()));
+    assert_eq!(write_calls, 3);
+    assert_eq!(clean_up_calls, 2);
+    let logs = in_memory_logger.drain_logs();
+    LogEntriesAssert::assert_that(logs)
+        .has_len(2)
+        .has_exactly_n_messages_containing(2, &Level::Warning, "disk busy");
+}
+
+#[test]
+fn should_return_error_of_last_attempt_after_exhausting_retries() {
+    let mut write_calls = 0;
+    let mut clean_up_calls = 0;
+
+    let result = write_with_retries(
+        &policy_with_max_attempts(2),
+        &no_op_logger(),
+        Path::new(STORE_FILE),
+        || {
+            write_calls += 1;
+            Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("disk busy {}", write_calls),
+            ))
+        },
+        || clean_up_calls += 1,
+    );
+
+    assert_matches!(result, Err(e) if e.to_string() == "disk busy 2");
+    assert_eq!(write_calls, 2);
+    assert_eq!(clean_up_calls, 2);
+}
+
+#[test]
+fn should_make_single_attempt_if_max_attempts_is_zero() {
+    let mut write_calls = 0;
+
+    let result = write_with_retries(
+        &policy_with_max_attempts(0),
+        &no_op_logger(),
+        Path::new(STORE_FILE),
+        || {
+            write_calls += 1;
+            Err(io::Error::new(io::ErrorKind::Other, "disk busy"))
+        },
+        || {},
+    );
+
+    assert_matches!(result, Err(_));
+    assert_eq!(write_calls, 1);
+}
+
+#[test]
+fn should_not_retry_with_no_retries_policy() {
+    let mut write_calls = 0;
+
+    let result = write_with_retries(
+        &StoreRetryPolicy::no_retries(),
+        &no_op_logger(),
+        Path::new(STORE_FILE),
+        || {
+            write_calls += 1;
+            Err(io::Error::new(io::ErrorKind::Other, "disk busy"))
+        },
+        || {},
+    );
+
+    assert_matches!(result, Err(_));
+    assert_eq!(write_calls, 1);
+}
+
+#[test]
+fn should_double_backoff_up_to_max_backoff() {
+    let max_backoff = Duration::from_millis(300);
+
+    assert_eq!(
+        next_backoff(Duration::from_millis(100), max_backoff),
+        Duration::from_millis(200)
+    );
+    assert_eq!(
+        next_backoff(Duration::from_millis(200), max_backoff),
+        max_backoff
+    );
+}
+
+#[test]
+fn should_not_overflow_when_doubling_very_large_backoff() {
+    assert_eq!(next_backoff(Duration::MAX, Duration::MAX), Duration::MAX);
+    assert_eq!(
+        next_backoff(Duration::MAX / 2 + Duration::from_secs(1), Duration::MAX),
+        Duration::MAX
+    );
+}
+
+mod proto_public_key_store {
+    use super::*;
+
+    const PUBLIC_KEYS_FILE: &str = "public_keys.pb";
+
+    #[test]
+    fn should_store_key_after_retrying_failed_writes() {
+        let temp_dir = mk_temp_dir_with_permissions(0o700);
+        let store_file = temp_dir.path().join(PUBLIC_KEYS_FILE);
+        let drain = Arc::new(RemoveDirAfterFailedWrites::new(&store_file, 2));
+        let mut store =
+            ProtoPublicKeyStore::open(temp_dir.path(), PUBLIC_KEYS_FILE, logger_for(&drain))
+                .with_write_retry_policy(policy_with_max_attempts(3));
+        make_writes_fail(&store_file);
+
+        assert_matches!(
+            store.set_once_node_signing_pubkey(valid_node_signing_public_key()),
+            Ok(())
+        );
+
+        assert_eq!(drain.failed_writes(), 2);
+        let reopened_store =
+            ProtoPublicKeyStore::open(temp_dir.path(), PUBLIC_KEYS_FILE, no_op_logger());
+        assert_eq!(
+            reopened_store.node_signing_pubkey(),
+            Some(valid_node_signing_public_key())
+        );
+    }
+
+    #[test]
+    fn should_return_error_after_exhausting_retries() {
+        let temp_dir = mk_temp_dir_with_permissions(0o700);
+        let store_file = temp_dir.path().join(PUBLIC_KEYS_FILE);
+        let drain = Arc::new(RemoveDirAfterFailedWrites::new(&store_file, usize::MAX));
+        let mut store =
+            ProtoPublicKeyStore::open(temp_dir.path(), PUBLIC_KEYS_FILE, logger_for(&drain))
+                .with_write_retry_policy(policy_with_max_attempts(3));
+        make_writes_fail(&store_file);
+
+        assert_matches!(
+            store.set_once_node_signing_pubkey(valid_node_signing_public_key()),
+            Err(PublicKeySetOnceError::Io(_))
+        );
+
+        assert_eq!(drain.failed_writes(), 3);
+    }
+}
+
+mod proto_secret_key_store {
+    use super::*;
+
+    const SKS_DATA_FILE: &str = "sks_data.pb";
+
+    #[test]
+    fn should_store_key_after_retrying_failed_writes() {
+        let temp_dir = mk_temp_dir_with_permissions(0o700);
+        let store_file = temp_dir.path().join(SKS_DATA_FILE);
+        let drain = Arc::new(RemoveDirAfterFailedWrites::new(&store_file, 2));
+        let mut store =
+            ProtoSecretKeyStore::open(temp_dir.path(), SKS_DATA_FILE, Some(logger_for(&drain)))
+                .with_write_retry_policy(policy_with_max_attempts(3));
+        make_writes_fail(&store_file);
+
+        assert_matches!(
+            store.insert(make_key_id(1), make_secret_key(1), None),
+            Ok(())
+        );
+
+        assert_eq!(drain.failed_writes(), 2);
+        let reopened_store = ProtoSecretKeyStore::open(temp_dir.path(), SKS_DATA_FILE, None);
+        assert!(reopened_store.contains(&make_key_id(1)));
+    }
+
+    #[test]
+    fn should_return_error_and_remove_temporary_file_after_exhausting_retries() {
+        let temp_dir = mk_temp_dir_with_permissions(0o700);
+        let store_file = temp_dir.path().join(SKS_DATA_FILE);
+        let drain = Arc::new(RemoveDirAfterFailedWrites::new(&store_file, usize::MAX));
+        let mut store =
+            ProtoSecretKeyStore::open(temp_dir.path(), SKS_DATA_FILE, Some(logger_for(&drain)))
+                .with_write_retry_policy(policy_with_max_attempts(3));
+        make_writes_fail(&store_file);
+
+        assert_matches!(
+            store.insert(make_key_id(1), make_secret_key(1), None),
+            Err(SecretKeyStoreInsertionError::TransientError(_))
+        );
+
+        assert_eq!(drain.failed_writes(), 3);
+        assert!(!ic_utils::fs::get_tmp_for_path(&store_file).exists());
+    }
+}
+
+/// Makes writes of a key store to `store_file` fail by creating a directory in its
+/// place, which the written temporary file cannot replace.
+fn make_writes_fail(store_file: &Path) {
+    fs::create_dir(store_file).expect("failed to create directory");
+}
+
+fn logger_for(drain: &Arc<RemoveDirAfterFailedWrites>) -> ReplicaLogger {
+    ReplicaLogger::from(LogEntryLogger::from(slog::Logger::root(
+        Arc::clone(drain),
+        slog::o!(),
+    )))
+}
+
+/// A log drain that counts the logged failed key store writes and removes
+/// `blocking_dir` once `failed_writes_until_removal` of them were logged, so that
+/// subsequent writes succeed.
+struct RemoveDirAfterFailedWrites {
+    blocking_dir: PathBuf,
+    failed_writes_until_removal: usize,
+    failed_writes: AtomicUsize,
+}
+
+impl RemoveDirAfterFailedWrites {
+    fn new(blocking_dir: &Path, failed_writes_until_removal: usize) -> Self {
+        RemoveDirAfterFailedWrites {
+            blocking_dir: blocking_dir.to_path_buf(),
+            failed_writes_until_removal,
+            failed_writes: AtomicUsize::new(0),
+        }
+    }
+
+    fn failed_writes(&self) -> usize {
+        self.failed_writes.load(Ordering::SeqCst)
+    }
+}
+
+impl slog::Drain for RemoveDirAfterFailedWrites {
+    type Ok = ();
+    type Err = slog::Never;
+
+    fn log(&self, record: &slog::Record, _values: &slog::OwnedKVList) -> Result<(), slog::Never> {
+        if record.level() == Level::Warning
+            && record.msg().to_string().contains("to write key store")
+        {
+            let failed_writes = self.failed_writes.fetch_add(1, Ordering::SeqCst) + 1;
+            if failed_writes == self.failed_writes_until_removal {
+                fs::remove_dir(&self.blocking_dir).expect("failed to remove directory");
+            }
+        }
+        Ok(())
+    }
+}
+
+fn policy_with_max_attempts(max_attempts: u32) -> StoreRetryPolicy {
+    StoreRetryPolicy {
+        max_attempts,
+        initial_backoff: Duration::ZERO,
+        max_backoff: Duration::ZERO,
+    }
+}
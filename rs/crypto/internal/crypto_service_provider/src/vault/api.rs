@@ -361,6 +361,10 @@ pub enum ValidatePksAndSksError {
     IdkgDealingEncryptionKeyError(ValidatePksAndSksKeyPairError),
     /// If a transient internal error occurs, e.g., an RPC error communicating with the remote vault
     TransientInternalError(String),
+    // Added last, since the variant index is part of the serialization used by the remote vault.
+    /// Public key store contains IDKG dealing encryption public keys, but no other public keys.
+    /// In particular, the node signing public key, which determines the node's identity, is missing.
+    OrphanedIdkgDealingEncryptionKeys,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -693,6 +697,7 @@ pub trait PublicAndSecretKeyStoreCspVault {
     /// The method return on the first encountered error and will not check further any other key pairs.
     /// The order in which checks are performed and keys are checked is not part of the API and should not be relied upon.
     /// * [`ValidatePksAndSksError::EmptyPublicKeyStore`] if there are no public keys
+    /// * [`ValidatePksAndSksError::OrphanedIdkgDealingEncryptionKeys`] if there are only IDKG dealing encryption public keys
     /// * [`ValidatePksAndSksError::NodeSigningKeyError`] if there is a problem with the node signing key pair
     /// * [`ValidatePksAndSksError::CommitteeSigningKeyError`] if there is a problem with the committee signing key pair
     /// * [`ValidatePksAndSksError::TlsCertificateError`] if there is a problem with the TLS key material
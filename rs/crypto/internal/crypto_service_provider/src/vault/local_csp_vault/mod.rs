@@ -16,6 +16,7 @@ use crate::public_key_store::proto_pubkey_store::ProtoPublicKeyStore;
 use crate::public_key_store::PublicKeyStore;
 use crate::secret_key_store::proto_store::ProtoSecretKeyStore;
 use crate::secret_key_store::SecretKeyStore;
+use crate::store_retry::StoreRetryPolicy;
 use crate::CspRwLock;
 use ic_crypto_internal_logmon::metrics::CryptoMetrics;
 use ic_crypto_internal_seed::Seed;
@@ -30,6 +31,16 @@ use std::collections::HashSet;
 use std::path::Path;
 use std::sync::Arc;
 
+/// Name of the file of the node secret key store in the key store directory of
+/// a [`LocalCspVault`] created with [`LocalCspVault::new_in_dir`].
+pub const SKS_DATA_FILENAME: &str = "sks_data.pb";
+/// Name of the file of the public key store in the key store directory of
+/// a [`LocalCspVault`] created with [`LocalCspVault::new_in_dir`].
+pub const PUBLIC_KEY_STORE_DATA_FILENAME: &str = "public_keys.pb";
+/// Name of the file of the canister secret key store in the key store directory of
+/// a [`LocalCspVault`] created with [`LocalCspVault::new_in_dir`].
+pub const CANISTER_SKS_DATA_FILENAME: &str = "canister_sks_data.pb";
+
 /// An implementation of `CspVault`-trait that runs in-process
 /// and uses local secret key stores.
 ///
@@ -121,26 +132,63 @@ impl ProdLocalCspVault {
         metrics: Arc<CryptoMetrics>,
         logger: ReplicaLogger,
     ) -> Self {
-        const SKS_DATA_FILENAME: &str = "sks_data.pb";
-        const PUBLIC_KEY_STORE_DATA_FILENAME: &str = "public_keys.pb";
-        const CANISTER_SKS_DATA_FILENAME: &str = "canister_sks_data.pb";
+        Self::new_in_dir_with_store_retry_policy(
+            key_store_dir,
+            StoreRetryPolicy::no_retries(),
+            metrics,
+            logger,
+        )
+    }
+
+    /// Like [`Self::new_in_dir`], but the key stores retry failed writes to disk
+    /// according to `store_retry_policy`.
+    pub fn new_in_dir_with_store_retry_policy(
+        key_store_dir: &Path,
+        store_retry_policy: StoreRetryPolicy,
+        metrics: Arc<CryptoMetrics>,
+        logger: ReplicaLogger,
+    ) -> Self {
+        Self::new_in_dir_with_rng(key_store_dir, OsRng, store_retry_policy, metrics, logger)
+    }
+}
 
+impl<R: Rng + CryptoRng>
+    LocalCspVault<R, ProtoSecretKeyStore, ProtoSecretKeyStore, ProtoPublicKeyStore>
+{
+    /// Like [`ProdLocalCspVault::new_in_dir_with_store_retry_policy`], but uses
+    /// `csprng` instead of the operating system as source of randomness.
+    ///
+    /// This allows generating reproducible key material, e.g., for tooling that
+    /// prepares test deployments. The `csprng` must be seeded from a secret source
+    /// if the generated keys are used in production.
+    pub fn new_in_dir_with_rng(
+        key_store_dir: &Path,
+        csprng: R,
+        store_retry_policy: StoreRetryPolicy,
+        metrics: Arc<CryptoMetrics>,
+        logger: ReplicaLogger,
+    ) -> Self {
         let node_secret_key_store =
-            ProtoSecretKeyStore::open(key_store_dir, SKS_DATA_FILENAME, Some(new_logger!(logger)));
+            ProtoSecretKeyStore::open(key_store_dir, SKS_DATA_FILENAME, Some(new_logger!(logger)))
+                .with_write_retry_policy(store_retry_policy.clone());
         let canister_secret_key_store = ProtoSecretKeyStore::open(
             key_store_dir,
             CANISTER_SKS_DATA_FILENAME,
             Some(new_logger!(logger)),
-        );
+        )
+        .with_write_retry_policy(store_retry_policy.clone());
         let public_key_store = ProtoPublicKeyStore::open(
             key_store_dir,
             PUBLIC_KEY_STORE_DATA_FILENAME,
             new_logger!(logger),
-        );
-        Self::new(
+        )
+        .with_write_retry_policy(store_retry_policy);
+        LocalCspVault::new_internal(
+            csprng,
             node_secret_key_store,
             canister_secret_key_store,
             public_key_store,
+            Arc::new(CurrentSystemTimeSource::new(new_logger!(&logger))),
             metrics,
             logger,
         )
@@ -278,6 +278,14 @@ impl LocalNodePublicKeys {
             && self.dkg_dealing_encryption_public_key.is_none()
             && self.idkg_dealing_encryption_public_keys.is_empty()
     }
+
+    fn contains_only_idkg_dealing_encryption_public_keys(&self) -> bool {
+        self.node_signing_public_key.is_none()
+            && self.committee_signing_public_key.is_none()
+            && self.tls_certificate.is_none()
+            && self.dkg_dealing_encryption_public_key.is_none()
+            && !self.idkg_dealing_encryption_public_keys.is_empty()
+    }
 }
 
 #[derive(Debug)]
@@ -297,6 +305,9 @@ impl TryFrom<LocalNodePublicKeys> for RequiredNodePublicKeys {
         if local_public_keys.is_empty() {
             return Err(ValidatePksAndSksError::EmptyPublicKeyStore);
         }
+        if local_public_keys.contains_only_idkg_dealing_encryption_public_keys() {
+            return Err(ValidatePksAndSksError::OrphanedIdkgDealingEncryptionKeys);
+        }
         let node_signing_public_key = local_public_keys.node_signing_public_key.ok_or(
             ValidatePksAndSksError::NodeSigningKeyError(PublicKeyNotFound),
         )?;
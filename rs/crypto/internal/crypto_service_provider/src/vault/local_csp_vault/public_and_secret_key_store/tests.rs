@@ -593,6 +593,29 @@ mod validate_pks_and_sks {
         assert_matches!(result, Err(ValidatePksAndSksError::EmptyPublicKeyStore))
     }
 
+    #[test]
+    fn should_return_orphaned_idkg_dealing_encryption_keys_when_only_idkg_keys() {
+        let vault = LocalCspVault::builder()
+            .with_mock_stores()
+            .with_public_key_store(public_key_store_containing_exactly(LocalNodePublicKeys {
+                node_signing_public_key: None,
+                committee_signing_public_key: None,
+                tls_certificate: None,
+                dkg_dealing_encryption_public_key: None,
+                idkg_dealing_encryption_public_keys: vec![
+                    valid_idkg_dealing_encryption_public_key(),
+                ],
+            }))
+            .build();
+
+        let result = vault.validate_pks_and_sks();
+
+        assert_matches!(
+            result,
+            Err(ValidatePksAndSksError::OrphanedIdkgDealingEncryptionKeys)
+        )
+    }
+
     #[test]
     fn should_return_public_key_not_found() {
         let tests = vec![
@@ -156,6 +156,16 @@ impl CryptoMetrics {
         }
     }
 
+    /// Observes the outcome of provisioning a node's keys.
+    pub fn observe_node_key_provisioning_outcome(&self, outcome: NodeKeyProvisioningOutcome) {
+        if let Some(metrics) = &self.metrics {
+            metrics
+                .crypto_node_key_provisioning_outcomes
+                .with_label_values(&[&format!("{}", outcome)])
+                .inc();
+        }
+    }
+
     /// Observes a situation where one or more keys in the registry are missing locally.
     pub fn observe_keys_in_registry_missing_locally(&self) {
         if let Some(metrics) = &self.metrics {
@@ -323,6 +333,15 @@ pub enum KeyRotationResult {
     PublicKeyNotFound,
 }
 
+/// Outcome of checking a node's key stores for node keys, generating them if missing.
+#[derive(Copy, Clone, Debug, EnumIter, Eq, IntoStaticStr, PartialOrd, Ord, PartialEq)]
+pub enum NodeKeyProvisioningOutcome {
+    /// No node keys existed, so all of them were generated.
+    GeneratedAll,
+    /// Consistent node keys existed and were reused.
+    ReusedExisting,
+}
+
 #[derive(Copy, Clone, Debug, EnumIter, Eq, IntoStaticStr, PartialOrd, Ord, PartialEq)]
 pub enum ServiceType {
     Client,
@@ -467,6 +486,10 @@ struct Metrics {
     /// performed, the outcome of the operation is tracked in this counter vector.
     pub crypto_key_rotation_results: IntCounterVec,
 
+    /// A counter vector for keeping track of the outcomes of provisioning a node's keys, e.g.,
+    /// whether keys were generated on first boot or reused on subsequent boots.
+    pub crypto_node_key_provisioning_outcomes: IntCounterVec,
+
     /// A counter for situations where one or more keys in the registry are missing locally.
     pub crypto_keys_in_registry_missing_locally_total: IntCounter,
 
@@ -526,6 +549,13 @@ impl Display for KeyRotationResult {
     }
 }
 
+impl Display for NodeKeyProvisioningOutcome {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let value: &'static str = self.into();
+        write!(f, "{}", value.to_case(Case::Snake))
+    }
+}
+
 impl Display for KeyType {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         let value: &'static str = self.into();
@@ -592,6 +622,14 @@ impl Metrics {
         for result in KeyRotationResult::iter() {
             rotation_results.with_label_values(&[&format!("{}", result)]);
         }
+        let provisioning_outcomes = r.int_counter_vec(
+            "crypto_node_key_provisioning_outcomes",
+            "Outcomes of provisioning the node keys",
+            &["outcome"],
+        );
+        for outcome in NodeKeyProvisioningOutcome::iter() {
+            provisioning_outcomes.with_label_values(&[&format!("{}", outcome)]);
+        }
         Self {
             crypto_lock_acquisition_duration_seconds: r.histogram_vec(
                 "crypto_lock_acquisition_duration_seconds",
@@ -622,6 +660,7 @@ impl Metrics {
             crypto_idkg_dealing_encryption_pubkey_count: idkg_dealing_encryption_pubkey_count,
             crypto_key_counts: key_counts,
             crypto_key_rotation_results: rotation_results,
+            crypto_node_key_provisioning_outcomes: provisioning_outcomes,
             crypto_keys_in_registry_missing_locally_total: r.int_counter(
                 "crypto_keys_in_registry_missing_locally_total",
                 "One or more keys in the registry is missing locally. This may occur if an adversary manages to register its keys on behalf of a node."
@@ -1,4 +1,4 @@
-use crate::metrics::{BooleanOperation, KeyType, MetricsDomain};
+use crate::metrics::{BooleanOperation, KeyType, MetricsDomain, NodeKeyProvisioningOutcome};
 
 #[test]
 fn shall_convert_enum_variants_to_snake_case_correctly() {
@@ -8,4 +8,8 @@ fn shall_convert_enum_variants_to_snake_case_correctly() {
     );
     assert_eq!("secret_sks", format!("{}", KeyType::SecretSKS));
     assert_eq!("idkg_protocol", format!("{}", MetricsDomain::IdkgProtocol));
+    assert_eq!(
+        "reused_existing",
+        format!("{}", NodeKeyProvisioningOutcome::ReusedExisting)
+    );
 }
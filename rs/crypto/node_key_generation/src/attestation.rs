@@ -0,0 +1,170 @@
+//! Attestation binding a node operator's statement to the node's keys.
+use crate::crypto_root::check_crypto_root;
+use crate::csp_for_config;
+use crate::key_ids::csp_public_key_id;
+use crate::missing_keys::{crypto_error, validate_pks_and_sks};
+use crate::NodeKeyFingerprints;
+use ic_config::crypto::CryptoConfig;
+use ic_crypto_internal_basic_sig_ed25519 as ed25519;
+use ic_crypto_internal_csp::api::CspSigner;
+use ic_crypto_internal_csp::types::{CspPublicKey, CspSignature};
+use ic_crypto_node_key_validation::ValidNodePublicKeys;
+use ic_crypto_sha::{Context, DomainSeparationContext, Sha256};
+use ic_types::crypto::{AlgorithmId, CryptoError, CryptoResult};
+use ic_types::NodeId;
+
+const KEY_ATTESTATION_DOMAIN: &str = "ic-node-key-attestation";
+
+/// A statement of a node operator, e.g., identifying the node's hardware, bound to
+/// the node's keys by a signature made with the node signing key.
+///
+/// Fingerprints are SHA-256 hashes of the respective public key bytes, and the TLS
+/// certificate hash is the SHA-256 hash of the DER-encoded certificate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyAttestation {
+    pub node_id: NodeId,
+    pub node_signing_key_fingerprint: [u8; 32],
+    pub committee_signing_key_fingerprint: [u8; 32],
+    pub dkg_dealing_encryption_key_fingerprint: [u8; 32],
+    pub idkg_dealing_encryption_key_fingerprint: [u8; 32],
+    pub tls_certificate_hash: [u8; 32],
+    pub operator_payload: Vec<u8>,
+    /// Ed25519 signature over the canonical serialization of all other fields.
+    pub signature: [u8; 64],
+}
+
+/// Creates a [`KeyAttestation`] of `operator_payload` for the node with the given `config`.
+///
+/// The signature is made by the CSP with the node signing key, i.e., the secret key
+/// never leaves the secret key store. As in
+/// [`generate_node_keys_once`](crate::generate_node_keys_once), a CSP is created
+/// according to `config`, which may use a remote vault.
+///
+/// # Errors
+/// * [`CryptoError::InvalidArgument`] if the crypto root is misconfigured.
+/// * [`CryptoError::InternalError`] if the node's key material is missing or inconsistent.
+/// * [`CryptoError::TransientInternalError`] if a transient internal error occurs, e.g.,
+///   an RPC error communicating with the remote vault.
+pub fn create_key_attestation(
+    config: &CryptoConfig,
+    tokio_runtime_handle: Option<tokio::runtime::Handle>,
+    operator_payload: &[u8],
+) -> CryptoResult<KeyAttestation> {
+    check_crypto_root(&config.crypto_root, false).map_err(crypto_error)?;
+    let csp = csp_for_config(config, tokio_runtime_handle);
+    let node_pks = validate_pks_and_sks(&csp)?;
+    let fingerprints = NodeKeyFingerprints::of(&node_pks);
+    let mut attestation = KeyAttestation {
+        node_id: node_pks.node_id(),
+        node_signing_key_fingerprint: fingerprints.node_signing_key,
+        committee_signing_key_fingerprint: fingerprints.committee_signing_key,
+        dkg_dealing_encryption_key_fingerprint: fingerprints.dkg_dealing_encryption_key,
+        idkg_dealing_encryption_key_fingerprint: fingerprints.idkg_dealing_encryption_key,
+        tls_certificate_hash: fingerprints.tls_certificate,
+        operator_payload: operator_payload.to_vec(),
+        signature: [0; 64],
+    };
+    let key_id = csp_public_key_id(node_pks.node_signing_key())?;
+    match csp.sign(AlgorithmId::Ed25519, &signed_bytes(&attestation), key_id)? {
+        CspSignature::Ed25519(signature) => attestation.signature = signature.0,
+        signature => {
+            return Err(CryptoError::InternalError {
+                internal_error: format!("unexpected signature type: {:?}", signature),
+            })
+        }
+    }
+    Ok(attestation)
+}
+
+/// Verifies that `attestation` was created for the node with the public keys
+/// `expected_node_pks`.
+///
+/// No secret key material is required for the verification.
+///
+/// # Errors
+/// * [`CryptoError::InvalidArgument`] if the node ID, a fingerprint, or the TLS
+///   certificate hash of `attestation` does not match `expected_node_pks`.
+/// * [`CryptoError::MalformedPublicKey`] if the node signing public key is malformed.
+/// * [`CryptoError::SignatureVerification`] if the signature is invalid.
+pub fn verify_key_attestation(
+    attestation: &KeyAttestation,
+    expected_node_pks: &ValidNodePublicKeys,
+) -> CryptoResult<()> {
+    if attestation.node_id != expected_node_pks.node_id() {
+        return Err(CryptoError::InvalidArgument {
+            message: format!(
+                "attestation is for node {}, but node {} was expected",
+                attestation.node_id,
+                expected_node_pks.node_id()
+            ),
+        });
+    }
+    for (name, hash, expected_bytes) in [
+        (
+            "node signing key fingerprint",
+            &attestation.node_signing_key_fingerprint,
+            &expected_node_pks.node_signing_key().key_value,
+        ),
+        (
+            "committee signing key fingerprint",
+            &attestation.committee_signing_key_fingerprint,
+            &expected_node_pks.committee_signing_key().key_value,
+        ),
+        (
+            "NI-DKG dealing encryption key fingerprint",
+            &attestation.dkg_dealing_encryption_key_fingerprint,
+            &expected_node_pks.dkg_dealing_encryption_key().key_value,
+        ),
+        (
+            "iDKG dealing encryption key fingerprint",
+            &attestation.idkg_dealing_encryption_key_fingerprint,
+            &expected_node_pks.idkg_dealing_encryption_key().key_value,
+        ),
+        (
+            "TLS certificate hash",
+            &attestation.tls_certificate_hash,
+            &expected_node_pks.tls_certificate().certificate_der,
+        ),
+    ] {
+        if *hash != Sha256::hash(expected_bytes) {
+            return Err(CryptoError::InvalidArgument {
+                message: format!("{} of attestation does not match", name),
+            });
+        }
+    }
+    match CspPublicKey::try_from(expected_node_pks.node_signing_key())? {
+        CspPublicKey::Ed25519(public_key) => ed25519::verify(
+            &ed25519::types::SignatureBytes(attestation.signature),
+            &signed_bytes(attestation),
+            &public_key,
+        ),
+        public_key => Err(CryptoError::MalformedPublicKey {
+            algorithm: AlgorithmId::Ed25519,
+            key_bytes: Some(expected_node_pks.node_signing_key().key_value.clone()),
+            internal_error: format!("unexpected node signing key type: {:?}", public_key),
+        }),
+    }
+}
+
+// Canonical serialization of all fields of `attestation` except the signature, where
+// each field is prefixed by its length as 8-byte big-endian integer.
+fn signed_bytes(attestation: &KeyAttestation) -> Vec<u8> {
+    let domain = DomainSeparationContext::new(KEY_ATTESTATION_DOMAIN);
+    let node_id = attestation.node_id.get();
+    let fields: [&[u8]; 8] = [
+        domain.as_bytes(),
+        node_id.as_slice(),
+        &attestation.node_signing_key_fingerprint,
+        &attestation.committee_signing_key_fingerprint,
+        &attestation.dkg_dealing_encryption_key_fingerprint,
+        &attestation.idkg_dealing_encryption_key_fingerprint,
+        &attestation.tls_certificate_hash,
+        &attestation.operator_payload,
+    ];
+    let mut bytes = Vec::new();
+    for field in fields {
+        bytes.extend_from_slice(&(field.len() as u64).to_be_bytes());
+        bytes.extend_from_slice(field);
+    }
+    bytes
+}
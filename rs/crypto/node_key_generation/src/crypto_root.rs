@@ -0,0 +1,76 @@
+//! Checks of a node's crypto root directory.
+use crate::NodeKeyGenerationError;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// Checks that `crypto_root` refers to an existing directory and returns the path of
+/// that directory.
+///
+/// If `crypto_root` is a symbolic link, it is only accepted if `allow_symlink` is `true`,
+/// in which case the link is resolved and the path of its (checked) target is returned.
+///
+/// # Errors
+/// * [`NodeKeyGenerationError::CryptoRootNotFound`] if `crypto_root` (or the target of
+///   an allowed symbolic link) does not exist, e.g., because the link is dangling.
+/// * [`NodeKeyGenerationError::CryptoRootNotADirectory`] if `crypto_root` (or the
+///   target of an allowed symbolic link) is not a directory.
+/// * [`NodeKeyGenerationError::CryptoRootIsSymlink`] if `crypto_root` is a symbolic
+///   link, but `allow_symlink` is `false`.
+///
+/// # Panics
+/// * if the metadata of `crypto_root` cannot be read for a reason other than
+///   `crypto_root` not existing (e.g., insufficient permissions).
+pub(crate) fn check_crypto_root(
+    crypto_root: &Path,
+    allow_symlink: bool,
+) -> Result<PathBuf, NodeKeyGenerationError> {
+    let metadata = match fs::symlink_metadata(crypto_root) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            return Err(NodeKeyGenerationError::CryptoRootNotFound {
+                crypto_root: crypto_root.to_path_buf(),
+            })
+        }
+        Err(e) => panic!(
+            "failed to read metadata of crypto root {}: {}",
+            crypto_root.display(),
+            e
+        ),
+    };
+    if metadata.file_type().is_symlink() {
+        let target = fs::read_link(crypto_root).unwrap_or_else(|e| {
+            panic!(
+                "failed to read target of symbolic link {}: {}",
+                crypto_root.display(),
+                e
+            )
+        });
+        if !allow_symlink {
+            return Err(NodeKeyGenerationError::CryptoRootIsSymlink {
+                crypto_root: crypto_root.to_path_buf(),
+                target,
+            });
+        }
+        let resolved_target = match fs::canonicalize(crypto_root) {
+            Ok(resolved_target) => resolved_target,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                return Err(NodeKeyGenerationError::CryptoRootNotFound {
+                    crypto_root: target,
+                })
+            }
+            Err(e) => panic!(
+                "failed to resolve symbolic link {}: {}",
+                crypto_root.display(),
+                e
+            ),
+        };
+        return check_crypto_root(&resolved_target, false);
+    }
+    if !metadata.is_dir() {
+        return Err(NodeKeyGenerationError::CryptoRootNotADirectory {
+            crypto_root: crypto_root.to_path_buf(),
+        });
+    }
+    Ok(crypto_root.to_path_buf())
+}
@@ -0,0 +1,66 @@
+//! Advisory locking of a node's crypto root directory.
+use crate::NodeKeyGenerationError;
+use ic_config::crypto::{CryptoConfig, CspVaultType};
+use nix::errno::Errno;
+use nix::fcntl::{flock, FlockArg};
+use std::fs::File;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+/// An exclusive advisory lock on a crypto root directory.
+///
+/// The lock prevents concurrent node key generation (e.g., by two processes racing
+/// after a restart) on the same crypto root, which could otherwise result in
+/// inconsistent key material. The lock is released when this struct is dropped.
+pub(crate) struct CryptoRootLock {
+    _crypto_root: File,
+}
+
+impl CryptoRootLock {
+    /// Tries to acquire the lock on `config.crypto_root` without blocking if `config`
+    /// uses an in-replica vault, and returns `None` otherwise.
+    ///
+    /// No lock is needed for a remote vault, since the vault process owns the key
+    /// stores and serializes the access to them. The crypto root of a remote vault
+    /// is typically not even readable by the calling process.
+    ///
+    /// # Errors
+    /// * [`NodeKeyGenerationError::Locked`] if the lock is currently held by someone else.
+    /// * [`NodeKeyGenerationError::CryptoRootLockFailed`] if the crypto root cannot be
+    ///   opened or locked.
+    pub(crate) fn try_acquire_for_in_replica_vault(
+        config: &CryptoConfig,
+    ) -> Result<Option<Self>, NodeKeyGenerationError> {
+        match config.csp_vault_type {
+            CspVaultType::InReplica => Self::try_acquire(&config.crypto_root).map(Some),
+            CspVaultType::UnixSocket(_) => Ok(None),
+        }
+    }
+
+    /// Tries to acquire the lock on `crypto_root` without blocking.
+    ///
+    /// # Errors
+    /// * [`NodeKeyGenerationError::Locked`] if the lock is currently held by someone else.
+    /// * [`NodeKeyGenerationError::CryptoRootLockFailed`] if the crypto root cannot be
+    ///   opened or locked.
+    pub(crate) fn try_acquire(crypto_root: &Path) -> Result<Self, NodeKeyGenerationError> {
+        let crypto_root_dir =
+            File::open(crypto_root).map_err(|e| NodeKeyGenerationError::CryptoRootLockFailed {
+                crypto_root: crypto_root.to_path_buf(),
+                internal_error: format!("failed to open crypto root for locking: {}", e),
+            })?;
+        match flock(crypto_root_dir.as_raw_fd(), FlockArg::LockExclusiveNonblock) {
+            Ok(()) => Ok(CryptoRootLock {
+                _crypto_root: crypto_root_dir,
+            }),
+            Err(Errno::EWOULDBLOCK) => Err(NodeKeyGenerationError::Locked(format!(
+                "crypto root {} is locked by a concurrent node key generation",
+                crypto_root.display()
+            ))),
+            Err(e) => Err(NodeKeyGenerationError::CryptoRootLockFailed {
+                crypto_root: crypto_root.to_path_buf(),
+                internal_error: format!("failed to lock crypto root: {}", e),
+            }),
+        }
+    }
+}
@@ -0,0 +1,58 @@
+//! Destruction of a node's secret key material when decommissioning the node.
+use crate::crypto_root::check_crypto_root;
+use crate::crypto_root_lock::CryptoRootLock;
+use crate::missing_keys::crypto_error;
+use crate::node_identity::remove_all_secret_keys;
+use ic_config::crypto::{CryptoConfig, CspVaultType};
+use ic_crypto_internal_csp::secret_key_store::SecretKeyStoreWriteError;
+use ic_types::crypto::{CryptoError, CryptoResult};
+
+/// Irreversibly removes all secret keys from the secret key stores at
+/// `config.crypto_root` and returns the number of removed keys.
+///
+/// This is intended for decommissioning a node: once the secret keys are removed,
+/// the node can no longer sign, decrypt, or establish TLS connections with its keys,
+/// and there is no way to recover them. The previous secret key store files are
+/// overwritten with zeroes before being deleted. The public key store is left
+/// untouched, so that the public keys of the decommissioned node remain available.
+///
+/// Only an in-replica vault (see [`CspVaultType::InReplica`]) is supported, i.e., for
+/// a remote vault, this function must be called with the config of the vault server.
+/// Since a running vault keeps the secret keys in memory and would write them back
+/// with its next update of the secret key store, no process using the key stores at
+/// `config.crypto_root` (e.g., the replica or the vault server) may be running.
+///
+/// The same lock as in [`generate_node_keys_once`](crate::generate_node_keys_once)
+/// is held for the duration of the call.
+///
+/// # Panics
+///  * if `config.crypto_root` does not have the [permissions required for storing crypto
+///    state](ic_config::crypto::CryptoConfig::check_dir_has_required_permissions).
+///
+/// # Errors
+/// * [`CryptoError::InvalidArgument`] if the crypto root is misconfigured, or if
+///   `config` does not use an in-replica vault.
+/// * [`CryptoError::InternalError`] if an updated secret key store cannot be serialized,
+///   or if the crypto root cannot be opened or locked.
+/// * [`CryptoError::TransientInternalError`] if the crypto root is locked by a
+///   concurrent call, or if an updated secret key store cannot be written.
+pub fn wipe_secret_keys(config: &CryptoConfig) -> CryptoResult<usize> {
+    if config.csp_vault_type != CspVaultType::InReplica {
+        return Err(CryptoError::InvalidArgument {
+            message: format!(
+                "secret keys can only be wiped for an in-replica vault, but the vault type is {:?}",
+                config.csp_vault_type
+            ),
+        });
+    }
+    check_crypto_root(&config.crypto_root, false).map_err(crypto_error)?;
+    let _lock = CryptoRootLock::try_acquire_for_in_replica_vault(config).map_err(crypto_error)?;
+    remove_all_secret_keys(&config.crypto_root).map_err(|(_path, error)| match error {
+        SecretKeyStoreWriteError::SerializationError(internal_error) => {
+            CryptoError::InternalError { internal_error }
+        }
+        SecretKeyStoreWriteError::TransientError(internal_error) => {
+            CryptoError::TransientInternalError { internal_error }
+        }
+    })
+}
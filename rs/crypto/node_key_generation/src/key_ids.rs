@@ -0,0 +1,90 @@
+//! Key identifiers of a node's keys in the secret key store.
+use ic_crypto_internal_csp::key_id::KeyId;
+use ic_crypto_internal_csp::keygen::utils::mega_public_key_from_proto;
+use ic_crypto_internal_csp::types::CspPublicKey;
+use ic_crypto_internal_types::sign::threshold_sig::ni_dkg::CspFsEncryptionPublicKey;
+use ic_crypto_tls_interfaces::TlsPublicKeyCert;
+use ic_protobuf::registry::crypto::v1::PublicKey as PublicKeyProto;
+use ic_types::crypto::{AlgorithmId, CryptoError, CryptoResult, CurrentNodePublicKeys};
+
+/// Identifiers under which the secret keys corresponding to a node's public keys
+/// are stored in the secret key store. A key ID is `None` if the corresponding
+/// public key is missing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NodeKeyIds {
+    pub node_signing_key_id: Option<KeyId>,
+    pub committee_signing_key_id: Option<KeyId>,
+    pub tls_secret_key_id: Option<KeyId>,
+    pub dkg_dealing_encryption_key_id: Option<KeyId>,
+    pub idkg_dealing_encryption_key_id: Option<KeyId>,
+}
+
+/// Computes the key IDs of the node's secret keys from the node's public keys.
+///
+/// The key IDs are derived exactly as done by the CSP when storing the secret keys,
+/// which allows correlating public keys with entries of the secret key store, e.g.,
+/// when debugging inconsistent key stores.
+///
+/// # Errors
+/// * [`CryptoError::MalformedPublicKey`] if a public key or the TLS certificate is malformed.
+/// * [`CryptoError::InvalidArgument`] if a key ID cannot be instantiated.
+pub fn key_ids_for_node_public_keys(node_pks: &CurrentNodePublicKeys) -> CryptoResult<NodeKeyIds> {
+    Ok(NodeKeyIds {
+        node_signing_key_id: node_pks
+            .node_signing_public_key
+            .as_ref()
+            .map(csp_public_key_id)
+            .transpose()?,
+        committee_signing_key_id: node_pks
+            .committee_signing_public_key
+            .as_ref()
+            .map(csp_public_key_id)
+            .transpose()?,
+        tls_secret_key_id: node_pks
+            .tls_certificate
+            .as_ref()
+            .map(|cert| {
+                let cert = TlsPublicKeyCert::try_from(cert.clone()).map_err(|e| {
+                    CryptoError::MalformedPublicKey {
+                        algorithm: AlgorithmId::Tls,
+                        key_bytes: Some(cert.certificate_der.clone()),
+                        internal_error: e.internal_error,
+                    }
+                })?;
+                Ok(KeyId::try_from(&cert)?)
+            })
+            .transpose()?,
+        dkg_dealing_encryption_key_id: node_pks
+            .dkg_dealing_encryption_public_key
+            .as_ref()
+            .map(|public_key| {
+                let public_key = CspFsEncryptionPublicKey::try_from(public_key).map_err(|e| {
+                    CryptoError::MalformedPublicKey {
+                        algorithm: AlgorithmId::Groth20_Bls12_381,
+                        key_bytes: Some(e.key_bytes),
+                        internal_error: e.internal_error,
+                    }
+                })?;
+                Ok(KeyId::from(&public_key))
+            })
+            .transpose()?,
+        idkg_dealing_encryption_key_id: node_pks
+            .idkg_dealing_encryption_public_key
+            .as_ref()
+            .map(|public_key| {
+                let malformed_public_key_error = |internal_error| CryptoError::MalformedPublicKey {
+                    algorithm: AlgorithmId::MegaSecp256k1,
+                    key_bytes: Some(public_key.key_value.clone()),
+                    internal_error,
+                };
+                let public_key = mega_public_key_from_proto(public_key)
+                    .map_err(|e| malformed_public_key_error(format!("{:?}", e)))?;
+                KeyId::try_from(&public_key).map_err(malformed_public_key_error)
+            })
+            .transpose()?,
+    })
+}
+
+pub(crate) fn csp_public_key_id(public_key: &PublicKeyProto) -> CryptoResult<KeyId> {
+    Ok(KeyId::try_from(&CspPublicKey::try_from(public_key)?)?)
+}
@@ -0,0 +1,113 @@
+//! Estimates of the on-disk footprint of a node's key material.
+
+// Estimated sizes in bytes of the serialized public key material as stored in the
+// public key store, including the protobuf framing and the generation timestamp.
+const NODE_SIGNING_PUBLIC_KEY_BYTES: usize = 45;
+const COMMITTEE_SIGNING_PUBLIC_KEY_BYTES: usize = 160;
+const TLS_CERTIFICATE_BYTES: usize = 355;
+const DKG_DEALING_ENCRYPTION_PUBLIC_KEY_BYTES: usize = 250;
+const IDKG_DEALING_ENCRYPTION_PUBLIC_KEY_BYTES: usize = 46;
+
+// Estimated sizes in bytes of the serialized secret key material as stored in the secret
+// key store, including the CBOR encoding of the key, the hex-encoded key ID,
+// and the protobuf framing of the store entry.
+const NODE_SIGNING_SECRET_KEY_BYTES: usize = 120;
+const COMMITTEE_SIGNING_SECRET_KEY_BYTES: usize = 125;
+const TLS_SECRET_KEY_BYTES: usize = 185;
+// Dominated by the forward-secure secret key, which upon generation consists of
+// a single binary tree encryption node holding 32 + 256 + 2 points in G2 and
+// one point in G1 (see `ic_crypto_internal_threshold_sig_bls12381::ni_dkg`).
+const DKG_DEALING_ENCRYPTION_SECRET_KEY_BYTES: usize = 29_400;
+const IDKG_DEALING_ENCRYPTION_SECRET_KEY_BYTES: usize = 175;
+
+/// Approximate size of the key material of a single key type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyTypeMaterialEstimate {
+    /// Approximate number of bytes the public key occupies in the public key store.
+    pub public_key_bytes: usize,
+    /// Approximate number of bytes the secret key occupies in the secret key store.
+    pub secret_key_bytes: usize,
+}
+
+impl KeyTypeMaterialEstimate {
+    pub fn total_bytes(&self) -> usize {
+        self.public_key_bytes + self.secret_key_bytes
+    }
+}
+
+/// Approximate size of the key material of a node with a full set of node keys.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyMaterialEstimate {
+    pub node_signing: KeyTypeMaterialEstimate,
+    pub committee_signing: KeyTypeMaterialEstimate,
+    pub tls: KeyTypeMaterialEstimate,
+    pub dkg_dealing_encryption: KeyTypeMaterialEstimate,
+    pub idkg_dealing_encryption: KeyTypeMaterialEstimate,
+}
+
+impl KeyMaterialEstimate {
+    /// Approximate size of the node's public key store.
+    pub fn public_key_store_bytes(&self) -> usize {
+        self.per_key_type()
+            .iter()
+            .map(|estimate| estimate.public_key_bytes)
+            .sum()
+    }
+
+    /// Approximate size of the node's secret key store.
+    pub fn secret_key_store_bytes(&self) -> usize {
+        self.per_key_type()
+            .iter()
+            .map(|estimate| estimate.secret_key_bytes)
+            .sum()
+    }
+
+    /// Approximate size of all key stores of the node.
+    pub fn total_bytes(&self) -> usize {
+        self.public_key_store_bytes() + self.secret_key_store_bytes()
+    }
+
+    fn per_key_type(&self) -> [KeyTypeMaterialEstimate; 5] {
+        [
+            self.node_signing,
+            self.committee_signing,
+            self.tls,
+            self.dkg_dealing_encryption,
+            self.idkg_dealing_encryption,
+        ]
+    }
+}
+
+/// Estimates the size of the key material of a node with a full set of node keys,
+/// as generated by [`generate_node_keys_once`](crate::generate_node_keys_once).
+///
+/// No keys are generated. The sizes are hard-coded estimates of the serialized keys
+/// of the used algorithms, which are checked per key type against the sizes of freshly
+/// generated key stores in tests. They are approximate only, because some encodings
+/// (e.g., of the TLS certificate or of the generation timestamps) vary slightly in
+/// length, and must be updated if the algorithms or encodings change.
+/// This is intended for capacity planning, e.g., when provisioning many nodes.
+pub fn estimate_key_material_size() -> KeyMaterialEstimate {
+    KeyMaterialEstimate {
+        node_signing: KeyTypeMaterialEstimate {
+            public_key_bytes: NODE_SIGNING_PUBLIC_KEY_BYTES,
+            secret_key_bytes: NODE_SIGNING_SECRET_KEY_BYTES,
+        },
+        committee_signing: KeyTypeMaterialEstimate {
+            public_key_bytes: COMMITTEE_SIGNING_PUBLIC_KEY_BYTES,
+            secret_key_bytes: COMMITTEE_SIGNING_SECRET_KEY_BYTES,
+        },
+        tls: KeyTypeMaterialEstimate {
+            public_key_bytes: TLS_CERTIFICATE_BYTES,
+            secret_key_bytes: TLS_SECRET_KEY_BYTES,
+        },
+        dkg_dealing_encryption: KeyTypeMaterialEstimate {
+            public_key_bytes: DKG_DEALING_ENCRYPTION_PUBLIC_KEY_BYTES,
+            secret_key_bytes: DKG_DEALING_ENCRYPTION_SECRET_KEY_BYTES,
+        },
+        idkg_dealing_encryption: KeyTypeMaterialEstimate {
+            public_key_bytes: IDKG_DEALING_ENCRYPTION_PUBLIC_KEY_BYTES,
+            secret_key_bytes: IDKG_DEALING_ENCRYPTION_SECRET_KEY_BYTES,
+        },
+    }
+}
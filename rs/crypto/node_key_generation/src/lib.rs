@@ -1,20 +1,61 @@
 //! Static crypto utility methods.
-use ic_config::crypto::CryptoConfig;
+use crate::crypto_root::check_crypto_root;
+use crate::crypto_root_lock::CryptoRootLock;
+use ic_config::crypto::{CryptoConfig, CspVaultType};
 use ic_crypto_internal_csp::api::CspCreateMEGaKeyError;
-use ic_crypto_internal_csp::vault::api::ValidatePksAndSksError;
+use ic_crypto_internal_csp::vault::api::{
+    CspBasicSignatureKeygenError, CspMultiSignatureKeygenError, CspPublicKeyStoreError,
+    CspTlsKeygenError, ValidatePksAndSksError,
+};
 use ic_crypto_internal_csp::CryptoServiceProvider;
 use ic_crypto_internal_csp::Csp;
 use ic_crypto_internal_logmon::metrics::CryptoMetrics;
+use ic_crypto_internal_threshold_sig_bls12381::api::ni_dkg_errors::CspDkgCreateFsKeyError;
 use ic_crypto_node_key_validation::ValidNodePublicKeys;
 use ic_crypto_tls_interfaces::TlsPublicKeyCert;
 use ic_crypto_utils_basic_sig::conversions as basicsig_conversions;
 use ic_interfaces::crypto::ErrorReproducibility;
+use ic_logger::{info, replica_logger::no_op_logger, ReplicaLogger};
 use ic_protobuf::registry::crypto::v1::PublicKey as PublicKeyProto;
 use ic_types::NodeId;
+use std::path::PathBuf;
 use std::sync::Arc;
 
+mod attestation;
+mod crypto_root;
+mod crypto_root_lock;
+mod decommission;
+mod key_ids;
+mod key_material_estimate;
+mod missing_keys;
+mod node_identity;
+mod provisioner;
+mod registry_diff;
 #[cfg(test)]
 mod tests;
+mod tls_certificate;
+
+pub use attestation::{create_key_attestation, verify_key_attestation, KeyAttestation};
+pub use decommission::wipe_secret_keys;
+pub use ic_crypto_internal_csp::store_retry::StoreRetryPolicy;
+pub use ic_crypto_internal_logmon::metrics::NodeKeyProvisioningOutcome;
+pub use key_ids::{key_ids_for_node_public_keys, NodeKeyIds};
+pub use key_material_estimate::{
+    estimate_key_material_size, KeyMaterialEstimate, KeyTypeMaterialEstimate,
+};
+pub use missing_keys::ensure_all_keys;
+pub use node_identity::{reset_node_identity, NodeIdentityResetError};
+pub use provisioner::{NodeKeyFingerprints, NodeKeyProvisioner, ProvisionedNode};
+pub use registry_diff::{diff_against_registry, KeyComparison, KeyDiff};
+pub use tls_certificate::{
+    check_tls_cert_validity, export_tls_cert_pem, generate_tls_keys_for_node_id,
+    generate_tls_keys_for_node_id_with_options, tls_cert_validity, CertValidity,
+    TlsKeyGenerationError, TlsKeyGenerationOptions,
+};
+
+// notAfter date of certificates without a well-defined expiration date, see RFC 5280
+// section 4.1.2.5 (https://tools.ietf.org/html/rfc5280#section-4.1.2.5).
+const RFC5280_NO_WELL_DEFINED_CERTIFICATE_EXPIRATION_DATE: &str = "99991231235959Z";
 
 fn derive_node_id(node_signing_pk: &PublicKeyProto) -> NodeId {
     basicsig_conversions::derive_node_id(node_signing_pk)
@@ -99,7 +140,7 @@ impl ErrorReproducibility for IDkgDealingEncryptionKeysGenerationError {
 /// 4.1.2.5; see https://tools.ietf.org/html/rfc5280#section-4.1.2.5) that the
 /// certificate has no well-defined expiration date.
 pub fn generate_tls_keys<T: CryptoServiceProvider>(csp: &T, node: NodeId) -> TlsPublicKeyCert {
-    csp.gen_tls_key_pair(node, "99991231235959Z")
+    csp.gen_tls_key_pair(node, RFC5280_NO_WELL_DEFINED_CERTIFICATE_EXPIRATION_DATE)
         .expect("error generating TLS key pair")
 }
 
@@ -123,8 +164,14 @@ pub fn generate_tls_keys<T: CryptoServiceProvider>(csp: &T, node: NodeId) -> Tls
 ///
 /// The `config.crypto_root` directory must exist and have the [permissions required
 /// for storing crypto state](CryptoConfig::check_dir_has_required_permissions).
+/// It must not be a symbolic link (see [`generate_node_keys_once_with_options`] to allow this).
 /// If there exists no key store in `config.crypto_root`, a new one is created.
 ///
+/// To prevent concurrent key generation on the same `config.crypto_root` (e.g., by two
+/// processes racing after a restart), an exclusive advisory lock on `config.crypto_root`
+/// is held for the duration of the call if `config` uses an in-replica vault. A remote
+/// vault serializes the access to its key stores itself.
+///
 /// # Panics
 ///  * if public keys exist but are inconsistent with the secret keys.
 ///  * if an error occurs when generating the keys.
@@ -133,55 +180,280 @@ pub fn generate_tls_keys<T: CryptoServiceProvider>(csp: &T, node: NodeId) -> Tls
 /// # Errors
 /// * [`NodeKeyGenerationError::TransientInternalError`] if a transient internal error occurs, e.g.,
 /// an RPC error communicating with the remote vault.
+/// * [`NodeKeyGenerationError::Locked`] if `config.crypto_root` is locked by a concurrent call.
+///   The crypto root is only locked for an in-replica vault, since a remote vault serializes
+///   the access to its key stores itself.
+/// * [`NodeKeyGenerationError::CryptoRootLockFailed`] if `config.crypto_root` of an in-replica
+///   vault cannot be opened or locked.
+/// * [`NodeKeyGenerationError::CryptoRootNotFound`] if `config.crypto_root` does not exist.
+/// * [`NodeKeyGenerationError::CryptoRootNotADirectory`] if `config.crypto_root` is not a directory.
+/// * [`NodeKeyGenerationError::CryptoRootIsSymlink`] if `config.crypto_root` is a symbolic link.
+/// * [`NodeKeyGenerationError::StoreFailed`] if a generated key could not be written to the
+///   key stores of an in-replica vault despite retrying according to the default
+///   [`StoreRetryPolicy`].
+/// * [`NodeKeyGenerationError::TlsKeyMaterialOnly`] if `config.crypto_root` only contains
+///   TLS key material generated by [`generate_tls_keys_for_node_id`].
 pub fn generate_node_keys_once(
     config: &CryptoConfig,
     tokio_runtime_handle: Option<tokio::runtime::Handle>,
 ) -> Result<ValidNodePublicKeys, NodeKeyGenerationError> {
-    let csp = csp_for_config(config, tokio_runtime_handle);
-    generate_node_keys_once_internal(&csp)
+    generate_node_keys_once_with_options(
+        config,
+        tokio_runtime_handle,
+        NodeKeyGenerationOptions::default(),
+    )
+}
+
+/// Options for the generation of node keys.
+#[derive(Clone, Default)]
+pub struct NodeKeyGenerationOptions {
+    /// Whether `config.crypto_root` may be a symbolic link. If so, the link is resolved
+    /// and its target, which must be an existing directory, is used as crypto root.
+    pub allow_symlinked_crypto_root: bool,
+    /// Logger used to report whether existing node keys were found or new ones were
+    /// generated, e.g., to detect unexpected key regeneration. Nothing is logged if `None`.
+    pub logger: Option<ReplicaLogger>,
+    /// Metrics into which the [`NodeKeyProvisioningOutcome`] is recorded. Nothing is
+    /// recorded if `None`.
+    pub metrics: Option<Arc<CryptoMetrics>>,
+    /// Policy for retrying failed writes of the key stores to disk. Only applies to an
+    /// in-replica vault, since the key stores of a remote vault are managed by the vault.
+    pub store_retry_policy: StoreRetryPolicy,
+}
+
+/// Like [`generate_node_keys_once`], but allows to customize the behavior via `options`.
+pub fn generate_node_keys_once_with_options(
+    config: &CryptoConfig,
+    tokio_runtime_handle: Option<tokio::runtime::Handle>,
+    options: NodeKeyGenerationOptions,
+) -> Result<ValidNodePublicKeys, NodeKeyGenerationError> {
+    generate_node_keys_once_with_outcome(config, tokio_runtime_handle, options)
+        .map(|(node_pks, _outcome)| node_pks)
+}
+
+/// Like [`generate_node_keys_once_with_options`], but additionally returns whether
+/// the node keys were generated or existing ones were reused.
+///
+/// If `options.metrics` is set, the outcome is also recorded there, which allows
+/// distinguishing a node's first boot from subsequent ones.
+pub fn generate_node_keys_once_with_outcome(
+    config: &CryptoConfig,
+    tokio_runtime_handle: Option<tokio::runtime::Handle>,
+    options: NodeKeyGenerationOptions,
+) -> Result<(ValidNodePublicKeys, NodeKeyProvisioningOutcome), NodeKeyGenerationError> {
+    generate_node_keys_once_with_csp_for_config(config, options, |config, store_retry_policy| {
+        csp_for_config_with_store_retry_policy(config, tokio_runtime_handle, store_retry_policy)
+    })
+}
+
+// Like `generate_node_keys_once_with_outcome`, but uses `csp_for_config` to create the
+// CSP for the checked `config` and the `options.store_retry_policy`.
+pub(crate) fn generate_node_keys_once_with_csp_for_config<F>(
+    config: &CryptoConfig,
+    options: NodeKeyGenerationOptions,
+    csp_for_config: F,
+) -> Result<(ValidNodePublicKeys, NodeKeyProvisioningOutcome), NodeKeyGenerationError>
+where
+    F: FnOnce(&CryptoConfig, StoreRetryPolicy) -> Csp,
+{
+    let config = CryptoConfig {
+        crypto_root: check_crypto_root(&config.crypto_root, options.allow_symlinked_crypto_root)?,
+        ..config.clone()
+    };
+    let _lock = CryptoRootLock::try_acquire_for_in_replica_vault(&config)?;
+    let csp = csp_for_config(&config, options.store_retry_policy.clone());
+    let logger = options.logger.unwrap_or_else(no_op_logger);
+    let (node_pks, outcome) = generate_node_keys_once_internal(
+        &csp,
+        &logger,
+        in_replica_store_retry_policy(&config, &options.store_retry_policy),
+    )?;
+    if let Some(metrics) = options.metrics {
+        metrics.observe_node_key_provisioning_outcome(outcome);
+    }
+    Ok((node_pks, outcome))
 }
 
+// `store_retry_policy` is the policy with which the key stores of the `csp`'s vault retry
+// failed writes, or `None` if the vault is remote.
 fn generate_node_keys_once_internal<T: CryptoServiceProvider>(
     csp: &T,
-) -> Result<ValidNodePublicKeys, NodeKeyGenerationError> {
+    logger: &ReplicaLogger,
+    store_retry_policy: Option<&StoreRetryPolicy>,
+) -> Result<(ValidNodePublicKeys, NodeKeyProvisioningOutcome), NodeKeyGenerationError> {
     match csp.validate_pks_and_sks() {
-        Ok(valid_public_keys) => Ok(valid_public_keys),
+        Ok(valid_public_keys) => {
+            info!(
+                logger,
+                "Found existing node keys for node {}",
+                valid_public_keys.node_id()
+            );
+            Ok((
+                valid_public_keys,
+                NodeKeyProvisioningOutcome::ReusedExisting,
+            ))
+        }
         Err(ValidatePksAndSksError::EmptyPublicKeyStore) => {
-            generate_all_node_keys(csp);
-            csp.validate_pks_and_sks().map_err(|error| match error {
+            generate_all_node_keys(csp, store_retry_policy)?;
+            let valid_public_keys = csp.validate_pks_and_sks().map_err(|error| match error {
                 ValidatePksAndSksError::TransientInternalError(transient_error) => {
                     NodeKeyGenerationError::TransientInternalError(transient_error)
                 }
                 _ => panic!("Node contains inconsistent key material: {:?}", error),
-            })
+            })?;
+            info!(
+                logger,
+                "Generated new node keys for node {}",
+                valid_public_keys.node_id()
+            );
+            Ok((valid_public_keys, NodeKeyProvisioningOutcome::GeneratedAll))
         }
         Err(ValidatePksAndSksError::TransientInternalError(transient_error)) => Err(
             NodeKeyGenerationError::TransientInternalError(transient_error),
         ),
-        Err(error) => panic!("Node contains inconsistent key material: {:?}", error),
+        Err(error) => {
+            if contains_only_tls_certificate(csp)? {
+                return Err(NodeKeyGenerationError::TlsKeyMaterialOnly);
+            }
+            panic!("Node contains inconsistent key material: {:?}", error)
+        }
     }
 }
 
-fn generate_all_node_keys<T: CryptoServiceProvider>(csp: &T) {
-    let node_signing_public_key = generate_node_signing_keys(csp);
-    let node_id = derive_node_id(&node_signing_public_key);
-    let _committee_signing_public_key = generate_committee_signing_keys(csp);
-    let _tls_certificate = generate_tls_keys(csp, node_id);
-    let _dkg_dealing_encryption_public_key = generate_dkg_dealing_encryption_keys(csp, node_id);
-    let _idkg_dealing_encryption_public_key = generate_idkg_dealing_encryption_keys(csp)
-        .unwrap_or_else(|e| panic!("Error generating I-DKG dealing encryption keys: {:?}", e));
+// Returns whether the TLS certificate is the only public key stored, as is the case
+// after `generate_tls_keys_for_node_id`.
+fn contains_only_tls_certificate<T: CryptoServiceProvider>(
+    csp: &T,
+) -> Result<bool, NodeKeyGenerationError> {
+    let current_node_public_keys = csp.current_node_public_keys().map_err(
+        |CspPublicKeyStoreError::TransientInternalError(e)| {
+            NodeKeyGenerationError::TransientInternalError(e)
+        },
+    )?;
+    Ok(current_node_public_keys.tls_certificate.is_some()
+        && current_node_public_keys.node_signing_public_key.is_none()
+        && current_node_public_keys
+            .committee_signing_public_key
+            .is_none()
+        && current_node_public_keys
+            .dkg_dealing_encryption_public_key
+            .is_none()
+        && current_node_public_keys
+            .idkg_dealing_encryption_public_key
+            .is_none())
+}
+
+// Generates all node keys. Failed writes of the generated keys are retried by the key
+// stores of the `csp`'s vault, so each key is generated exactly once.
+fn generate_all_node_keys<T: CryptoServiceProvider>(
+    csp: &T,
+    store_retry_policy: Option<&StoreRetryPolicy>,
+) -> Result<(), NodeKeyGenerationError> {
+    let node_signing_public_key = csp.gen_node_signing_key_pair().map_err(|e| match e {
+        CspBasicSignatureKeygenError::TransientInternalError { internal_error } => {
+            transient_keygen_error(internal_error, store_retry_policy)
+        }
+        _ => panic!("Could not generate node signing keys: {:?}", e),
+    })?;
+    let node_id = derive_node_id(
+        &ic_crypto_internal_csp::keygen::utils::node_signing_pk_to_proto(node_signing_public_key),
+    );
+    csp.gen_committee_signing_key_pair().map_err(|e| match e {
+        CspMultiSignatureKeygenError::TransientInternalError { internal_error } => {
+            transient_keygen_error(internal_error, store_retry_policy)
+        }
+        _ => panic!("Could not generate committee signing keys: {:?}", e),
+    })?;
+    csp.gen_tls_key_pair(node_id, RFC5280_NO_WELL_DEFINED_CERTIFICATE_EXPIRATION_DATE)
+        .map_err(|e| match e {
+            CspTlsKeygenError::TransientInternalError { internal_error } => {
+                transient_keygen_error(internal_error, store_retry_policy)
+            }
+            _ => panic!("error generating TLS key pair: {:?}", e),
+        })?;
+    csp.gen_dealing_encryption_key_pair(node_id)
+        .map_err(|e| match e {
+            CspDkgCreateFsKeyError::TransientInternalError(internal_error) => {
+                transient_keygen_error(internal_error, store_retry_policy)
+            }
+            _ => panic!("Failed to generate DKG dealing encryption keys: {:?}", e),
+        })?;
+    generate_idkg_dealing_encryption_keys(csp).map_err(|e| match e {
+        IDkgDealingEncryptionKeysGenerationError::TransientInternalError(internal_error) => {
+            transient_keygen_error(internal_error, store_retry_policy)
+        }
+        IDkgDealingEncryptionKeysGenerationError::InternalError(_) => {
+            panic!("Error generating I-DKG dealing encryption keys: {:?}", e)
+        }
+    })?;
+    Ok(())
+}
+
+// With an in-replica vault, key generation only fails transiently if the generated key
+// could not be written to the key stores, which retried the write according to the
+// `store_retry_policy`.
+fn transient_keygen_error(
+    internal_error: String,
+    store_retry_policy: Option<&StoreRetryPolicy>,
+) -> NodeKeyGenerationError {
+    match store_retry_policy {
+        Some(policy) => NodeKeyGenerationError::StoreFailed {
+            attempts: policy.max_attempts.max(1),
+            internal_error,
+        },
+        None => NodeKeyGenerationError::TransientInternalError(internal_error),
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum NodeKeyGenerationError {
     /// If a transient internal error occurs, e.g., an RPC error communicating with the remote vault
     TransientInternalError(String),
+    /// If the crypto root is locked by a concurrent node key generation
+    Locked(String),
+    /// If the crypto root does not exist
+    CryptoRootNotFound { crypto_root: PathBuf },
+    /// If the crypto root is not a directory
+    CryptoRootNotADirectory { crypto_root: PathBuf },
+    /// If the crypto root is a symbolic link, but symbolic links are not allowed
+    CryptoRootIsSymlink {
+        crypto_root: PathBuf,
+        target: PathBuf,
+    },
+    /// If a key could not be written to the key stores of an in-replica vault despite
+    /// retrying (see [`StoreRetryPolicy`])
+    StoreFailed {
+        attempts: u32,
+        internal_error: String,
+    },
+    /// If the crypto root only contains TLS key material, e.g., generated by
+    /// [`generate_tls_keys_for_node_id`], which cannot be complemented by the other
+    /// node keys, since the node ID is derived from a newly generated node signing key
+    TlsKeyMaterialOnly,
+    /// If the crypto root of an in-replica vault cannot be opened or locked, e.g.,
+    /// because of missing permissions
+    CryptoRootLockFailed {
+        crypto_root: PathBuf,
+        internal_error: String,
+    },
 }
 
 impl ErrorReproducibility for NodeKeyGenerationError {
     fn is_reproducible(&self) -> bool {
         match self {
             NodeKeyGenerationError::TransientInternalError(_) => false,
+            // false, since the lock is released once the concurrent generation completes
+            NodeKeyGenerationError::Locked(_) => false,
+            // false, since the failed key store writes may succeed later
+            NodeKeyGenerationError::StoreFailed { .. } => false,
+            // true, since a misconfigured crypto root remains misconfigured
+            NodeKeyGenerationError::CryptoRootNotFound { .. }
+            | NodeKeyGenerationError::CryptoRootNotADirectory { .. }
+            | NodeKeyGenerationError::CryptoRootIsSymlink { .. } => true,
+            // true, since the TLS key material is never removed
+            NodeKeyGenerationError::TlsKeyMaterialOnly => true,
+            // true, since the permissions of the crypto root remain the same
+            NodeKeyGenerationError::CryptoRootLockFailed { .. } => true,
         }
     }
 }
@@ -190,10 +462,35 @@ fn csp_for_config(
     config: &CryptoConfig,
     tokio_runtime_handle: Option<tokio::runtime::Handle>,
 ) -> Csp {
-    Csp::new(
+    csp_for_config_with_store_retry_policy(
+        config,
+        tokio_runtime_handle,
+        StoreRetryPolicy::no_retries(),
+    )
+}
+
+fn csp_for_config_with_store_retry_policy(
+    config: &CryptoConfig,
+    tokio_runtime_handle: Option<tokio::runtime::Handle>,
+    store_retry_policy: StoreRetryPolicy,
+) -> Csp {
+    Csp::new_with_store_retry_policy(
         config,
         tokio_runtime_handle,
         None,
         Arc::new(CryptoMetrics::none()),
+        store_retry_policy,
     )
 }
+
+// Returns the `store_retry_policy` if the key stores are managed by an in-replica vault
+// created for `config`, and `None` otherwise.
+fn in_replica_store_retry_policy<'a>(
+    config: &CryptoConfig,
+    store_retry_policy: &'a StoreRetryPolicy,
+) -> Option<&'a StoreRetryPolicy> {
+    match config.csp_vault_type {
+        CspVaultType::InReplica => Some(store_retry_policy),
+        CspVaultType::UnixSocket(_) => None,
+    }
+}
@@ -0,0 +1,167 @@
+//! Generation of a node's missing keys, e.g., after an incomplete restore of its key stores.
+use crate::crypto_root::check_crypto_root;
+use crate::crypto_root_lock::CryptoRootLock;
+use crate::{
+    csp_for_config, derive_node_id, generate_idkg_dealing_encryption_keys,
+    IDkgDealingEncryptionKeysGenerationError, NodeKeyGenerationError,
+    RFC5280_NO_WELL_DEFINED_CERTIFICATE_EXPIRATION_DATE,
+};
+use ic_config::crypto::CryptoConfig;
+use ic_crypto_internal_csp::api::CspPublicKeyStore;
+use ic_crypto_internal_csp::vault::api::{
+    CspMultiSignatureKeygenError, CspTlsKeygenError, ValidatePksAndSksError,
+};
+use ic_crypto_internal_csp::CryptoServiceProvider;
+use ic_crypto_internal_threshold_sig_bls12381::api::ni_dkg_errors::CspDkgCreateFsKeyError;
+use ic_crypto_node_key_validation::ValidNodePublicKeys;
+use ic_types::crypto::{CryptoError, CryptoResult};
+use ic_types::NodeId;
+
+/// Generates those of the node's keys that are missing in the key stores of the
+/// node with the given `config`, leaving all existing keys untouched, and returns the
+/// validated node public keys.
+///
+/// As in [`generate_node_keys_once`](crate::generate_node_keys_once), a CSP is created
+/// according to `config`, i.e., the keys are generated and stored by the configured
+/// vault, which may be a remote vault.
+///
+/// The TLS certificate and the NI-DKG dealing encryption key are bound to
+/// `node_id`, which must be the node ID derived from the stored node signing
+/// public key. Since the node ID is derived from the node signing key, a missing
+/// node signing key cannot be generated for `node_id`; use
+/// [`generate_node_keys_once`](crate::generate_node_keys_once) to provision a
+/// node from scratch instead. Calling this function on a fully provisioned node
+/// generates no keys.
+///
+/// If `config` uses an in-replica vault, the same lock as in
+/// [`generate_node_keys_once`](crate::generate_node_keys_once) is held for the
+/// duration of the call.
+///
+/// # Panics
+///  * if a non-transient error occurs when generating the keys.
+///
+/// # Errors
+/// * [`CryptoError::InvalidArgument`] if the crypto root is misconfigured, if no node
+///   signing public key is stored, or if the stored one does not match `node_id`.
+/// * [`CryptoError::InternalError`] if the resulting key material is inconsistent,
+///   e.g., because a public key exists whose secret key is missing, or if the crypto
+///   root cannot be opened or locked.
+/// * [`CryptoError::TransientInternalError`] if the crypto root is locked by a
+///   concurrent call, or if a transient internal error occurs, e.g., an RPC error
+///   communicating with the remote vault.
+pub fn ensure_all_keys(
+    config: &CryptoConfig,
+    tokio_runtime_handle: Option<tokio::runtime::Handle>,
+    node_id: NodeId,
+) -> CryptoResult<ValidNodePublicKeys> {
+    check_crypto_root(&config.crypto_root, false).map_err(crypto_error)?;
+    let _lock = CryptoRootLock::try_acquire_for_in_replica_vault(config).map_err(crypto_error)?;
+    let csp = csp_for_config(config, tokio_runtime_handle);
+    ensure_all_keys_internal(&csp, node_id)
+}
+
+pub(crate) fn ensure_all_keys_internal<T: CryptoServiceProvider>(
+    csp: &T,
+    node_id: NodeId,
+) -> CryptoResult<ValidNodePublicKeys> {
+    let current_node_public_keys = csp.current_node_public_keys()?;
+
+    match &current_node_public_keys.node_signing_public_key {
+        Some(node_signing_public_key) if derive_node_id(node_signing_public_key) == node_id => {}
+        Some(node_signing_public_key) => {
+            return Err(CryptoError::InvalidArgument {
+                message: format!(
+                    "node ID {} derived from the stored node signing public key does not match {}",
+                    derive_node_id(node_signing_public_key),
+                    node_id
+                ),
+            })
+        }
+        None => {
+            return Err(CryptoError::InvalidArgument {
+                message: format!(
+                    "no node signing public key stored, so no keys can be generated for node {}",
+                    node_id
+                ),
+            })
+        }
+    }
+    if current_node_public_keys
+        .committee_signing_public_key
+        .is_none()
+    {
+        csp.gen_committee_signing_key_pair().map_err(|e| match e {
+            CspMultiSignatureKeygenError::TransientInternalError { internal_error } => {
+                CryptoError::TransientInternalError { internal_error }
+            }
+            _ => panic!("Could not generate committee signing keys: {:?}", e),
+        })?;
+    }
+    if current_node_public_keys.tls_certificate.is_none() {
+        csp.gen_tls_key_pair(node_id, RFC5280_NO_WELL_DEFINED_CERTIFICATE_EXPIRATION_DATE)
+            .map_err(|e| match e {
+                CspTlsKeygenError::TransientInternalError { internal_error } => {
+                    CryptoError::TransientInternalError { internal_error }
+                }
+                _ => panic!("error generating TLS key pair: {:?}", e),
+            })?;
+    }
+    if current_node_public_keys
+        .dkg_dealing_encryption_public_key
+        .is_none()
+    {
+        csp.gen_dealing_encryption_key_pair(node_id)
+            .map_err(|e| match e {
+                CspDkgCreateFsKeyError::TransientInternalError(internal_error) => {
+                    CryptoError::TransientInternalError { internal_error }
+                }
+                _ => panic!("Failed to generate DKG dealing encryption keys: {:?}", e),
+            })?;
+    }
+    if current_node_public_keys
+        .idkg_dealing_encryption_public_key
+        .is_none()
+    {
+        generate_idkg_dealing_encryption_keys(csp).map_err(|e| match e {
+            IDkgDealingEncryptionKeysGenerationError::TransientInternalError(internal_error) => {
+                CryptoError::TransientInternalError { internal_error }
+            }
+            IDkgDealingEncryptionKeysGenerationError::InternalError(_) => {
+                panic!("Error generating I-DKG dealing encryption keys: {:?}", e)
+            }
+        })?;
+    }
+    validate_pks_and_sks(csp)
+}
+
+pub(crate) fn validate_pks_and_sks<T: CryptoServiceProvider>(
+    csp: &T,
+) -> CryptoResult<ValidNodePublicKeys> {
+    csp.validate_pks_and_sks().map_err(|error| match error {
+        ValidatePksAndSksError::TransientInternalError(internal_error) => {
+            CryptoError::TransientInternalError { internal_error }
+        }
+        _ => CryptoError::InternalError {
+            internal_error: format!("Node contains inconsistent key material: {:?}", error),
+        },
+    })
+}
+
+pub(crate) fn crypto_error(error: NodeKeyGenerationError) -> CryptoError {
+    match error {
+        NodeKeyGenerationError::TransientInternalError(internal_error)
+        | NodeKeyGenerationError::Locked(internal_error)
+        | NodeKeyGenerationError::StoreFailed { internal_error, .. } => {
+            CryptoError::TransientInternalError { internal_error }
+        }
+        NodeKeyGenerationError::CryptoRootNotFound { .. }
+        | NodeKeyGenerationError::CryptoRootNotADirectory { .. }
+        | NodeKeyGenerationError::CryptoRootIsSymlink { .. }
+        | NodeKeyGenerationError::TlsKeyMaterialOnly => CryptoError::InvalidArgument {
+            message: format!("{:?}", error),
+        },
+        NodeKeyGenerationError::CryptoRootLockFailed { .. } => CryptoError::InternalError {
+            internal_error: format!("{:?}", error),
+        },
+    }
+}
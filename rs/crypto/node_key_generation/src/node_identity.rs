@@ -0,0 +1,184 @@
+//! Re-provisioning of a node with a new identity.
+use crate::crypto_root::check_crypto_root;
+use crate::crypto_root_lock::CryptoRootLock;
+use crate::{
+    csp_for_config, csp_for_config_with_store_retry_policy, derive_node_id,
+    generate_node_keys_once_internal,
+};
+use crate::{NodeKeyGenerationError, NodeKeyGenerationOptions};
+use ic_config::crypto::{CryptoConfig, CspVaultType};
+use ic_crypto_internal_csp::api::CspPublicKeyStore;
+use ic_crypto_internal_csp::secret_key_store::proto_store::ProtoSecretKeyStore;
+use ic_crypto_internal_csp::secret_key_store::SecretKeyStoreWriteError;
+use ic_crypto_internal_csp::vault::api::CspPublicKeyStoreError;
+use ic_crypto_internal_csp::vault::local_csp_vault::{
+    CANISTER_SKS_DATA_FILENAME, PUBLIC_KEY_STORE_DATA_FILENAME, SKS_DATA_FILENAME,
+};
+use ic_crypto_node_key_validation::ValidNodePublicKeys;
+use ic_logger::replica_logger::no_op_logger;
+use ic_types::NodeId;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+const SECRET_KEY_STORE_FILES: [&str; 2] = [SKS_DATA_FILENAME, CANISTER_SKS_DATA_FILENAME];
+
+/// Replaces the identity of the node whose key material is stored in
+/// `config.crypto_root` by a new one.
+///
+/// The node ID derived from the node signing public key on disk must equal
+/// `expected_old_node_id`, which prevents resetting the identity of the wrong node.
+/// Then, all existing key material is removed, and a full set of fresh node keys
+/// is generated, as done by [`generate_node_keys_once`](crate::generate_node_keys_once).
+/// The secret keys are removed with [`ProtoSecretKeyStore::remove_all`], i.e., the
+/// previous secret key store files are overwritten with zeroes before being deleted.
+///
+/// The public key store is removed last. Hence, if the call is interrupted
+/// while removing the key material, the public key store still identifies the
+/// old node and the reset can be completed by calling this function again with
+/// the same `expected_old_node_id`. If the call is interrupted after removing
+/// all key material, the crypto root is clean and a subsequent call of
+/// [`generate_node_keys_once`](crate::generate_node_keys_once) provisions a new node.
+///
+/// The same lock as in [`generate_node_keys_once`](crate::generate_node_keys_once)
+/// is held for the duration of the call.
+///
+/// As in [`generate_node_keys_once_with_options`](crate::generate_node_keys_once_with_options),
+/// the `options` control whether `config.crypto_root` may be a symbolic link, where
+/// the generation of the new keys is logged and its outcome is recorded, and how failed
+/// key store writes are retried.
+///
+/// # Panics
+///  * if an error occurs when generating the keys.
+///
+/// # Errors
+/// * [`NodeIdentityResetError::NodeIdMismatch`] if the node ID on disk is not
+///   `expected_old_node_id` (or if no node signing public key is stored).
+/// * [`NodeIdentityResetError::UnsupportedVaultType`] if `config` uses a remote
+///   vault, whose key material cannot be removed locally.
+/// * [`NodeIdentityResetError::KeyMaterialRemovalFailed`] if a key store file cannot
+///   be removed.
+/// * [`NodeIdentityResetError::KeyGenerationError`] if the crypto root is
+///   misconfigured, locked, or cannot be locked, or if a transient error occurs.
+pub fn reset_node_identity(
+    config: &CryptoConfig,
+    tokio_runtime_handle: Option<tokio::runtime::Handle>,
+    expected_old_node_id: NodeId,
+    options: NodeKeyGenerationOptions,
+) -> Result<ValidNodePublicKeys, NodeIdentityResetError> {
+    if config.csp_vault_type != CspVaultType::InReplica {
+        return Err(NodeIdentityResetError::UnsupportedVaultType(
+            config.csp_vault_type.clone(),
+        ));
+    }
+    let config = CryptoConfig {
+        crypto_root: check_crypto_root(&config.crypto_root, options.allow_symlinked_crypto_root)?,
+        ..config.clone()
+    };
+    let _lock = CryptoRootLock::try_acquire_for_in_replica_vault(&config)?;
+
+    let found_node_id = current_node_id(&config)?;
+    if found_node_id != Some(expected_old_node_id) {
+        return Err(NodeIdentityResetError::NodeIdMismatch {
+            expected: expected_old_node_id,
+            found: found_node_id,
+        });
+    }
+
+    remove_node_key_material(&config.crypto_root)?;
+
+    let csp = csp_for_config_with_store_retry_policy(
+        &config,
+        tokio_runtime_handle,
+        options.store_retry_policy.clone(),
+    );
+    let logger = options.logger.unwrap_or_else(no_op_logger);
+    let (node_pks, outcome) =
+        generate_node_keys_once_internal(&csp, &logger, Some(&options.store_retry_policy))?;
+    if let Some(metrics) = options.metrics {
+        metrics.observe_node_key_provisioning_outcome(outcome);
+    }
+    Ok(node_pks)
+}
+
+/// Errors returned by [`reset_node_identity`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NodeIdentityResetError {
+    /// If the node ID on disk does not match the expected one
+    NodeIdMismatch {
+        expected: NodeId,
+        found: Option<NodeId>,
+    },
+    /// If the vault type does not allow removing the key material locally
+    UnsupportedVaultType(CspVaultType),
+    /// If a key store file could not be removed
+    KeyMaterialRemovalFailed {
+        path: PathBuf,
+        internal_error: String,
+    },
+    /// If the generation of the new node keys failed
+    KeyGenerationError(NodeKeyGenerationError),
+}
+
+impl From<NodeKeyGenerationError> for NodeIdentityResetError {
+    fn from(error: NodeKeyGenerationError) -> Self {
+        NodeIdentityResetError::KeyGenerationError(error)
+    }
+}
+
+fn current_node_id(config: &CryptoConfig) -> Result<Option<NodeId>, NodeIdentityResetError> {
+    let csp = csp_for_config(config, None);
+    let current_node_public_keys = csp.current_node_public_keys().map_err(
+        |CspPublicKeyStoreError::TransientInternalError(e)| {
+            NodeKeyGenerationError::TransientInternalError(e)
+        },
+    )?;
+    Ok(current_node_public_keys
+        .node_signing_public_key
+        .as_ref()
+        .map(derive_node_id))
+}
+
+/// Removes all secret keys from the secret key stores of a local vault at `crypto_root`
+/// and returns the number of removed keys.
+///
+/// Returns the path of the secret key store file and the error if a secret key store
+/// cannot be updated.
+pub(crate) fn remove_all_secret_keys(
+    crypto_root: &Path,
+) -> Result<usize, (PathBuf, SecretKeyStoreWriteError)> {
+    let mut removed_keys_count = 0;
+    for file_name in SECRET_KEY_STORE_FILES {
+        let mut secret_key_store = ProtoSecretKeyStore::open(crypto_root, file_name, None);
+        removed_keys_count += secret_key_store
+            .remove_all()
+            .map_err(|e| (secret_key_store.proto_file_path().to_path_buf(), e))?;
+    }
+    Ok(removed_keys_count)
+}
+
+// Removes all key material of the node at `crypto_root`, where the public key store
+// is removed last (see `reset_node_identity`).
+pub(crate) fn remove_node_key_material(crypto_root: &Path) -> Result<(), NodeIdentityResetError> {
+    remove_all_secret_keys(crypto_root).map_err(|(path, error)| {
+        NodeIdentityResetError::KeyMaterialRemovalFailed {
+            path,
+            internal_error: format!("{:?}", error),
+        }
+    })?;
+    remove_if_exists(&crypto_root.join(PUBLIC_KEY_STORE_DATA_FILENAME))
+}
+
+fn remove_if_exists(path: &Path) -> Result<(), NodeIdentityResetError> {
+    match std::fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(removal_failed_error(path, e)),
+    }
+}
+
+fn removal_failed_error(path: &Path, error: std::io::Error) -> NodeIdentityResetError {
+    NodeIdentityResetError::KeyMaterialRemovalFailed {
+        path: path.to_path_buf(),
+        internal_error: error.to_string(),
+    }
+}
@@ -0,0 +1,198 @@
+//! Provisioning of node keys for many nodes, e.g., by tooling preparing a new subnet.
+use crate::{
+    generate_node_keys_once_with_csp_for_config, generate_node_keys_once_with_options,
+    key_ids_for_node_public_keys, NodeKeyGenerationError, NodeKeyGenerationOptions, NodeKeyIds,
+};
+use ic_config::crypto::CryptoConfig;
+use ic_crypto_internal_csp::Csp;
+use ic_crypto_internal_logmon::metrics::CryptoMetrics;
+use ic_crypto_node_key_validation::ValidNodePublicKeys;
+use ic_crypto_sha::Sha256;
+use ic_types::crypto::CurrentNodePublicKeys;
+use ic_types::NodeId;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use std::fs::{self, Permissions};
+use std::io::ErrorKind;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Provisions node keys into one or more crypto roots.
+///
+/// In contrast to a [`Csp`](ic_crypto_internal_csp::Csp), which is bound to a single
+/// crypto root, a provisioner can be reused to provision the keys of many nodes (see
+/// [`NodeKeyProvisioner::provision_into`]). Only the public outputs are returned, so that
+/// no CSP needs to be kept alive after a node has been provisioned.
+///
+/// The keys are always generated by an in-replica vault, since the crypto roots are
+/// local directories. All node keys are generated, with a TLS certificate without a
+/// well-defined expiration date (see [`generate_tls_keys`](crate::generate_tls_keys)).
+#[derive(Clone, Default)]
+pub struct NodeKeyProvisioner {
+    crypto_root: Option<PathBuf>,
+    rng: Option<Arc<Mutex<ChaCha20Rng>>>,
+    options: NodeKeyGenerationOptions,
+}
+
+impl NodeKeyProvisioner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the crypto root used by [`NodeKeyProvisioner::provision`].
+    pub fn with_crypto_root(mut self, crypto_root: PathBuf) -> Self {
+        self.crypto_root = Some(crypto_root);
+        self
+    }
+
+    /// Makes the provisioned keys reproducible by deriving the randomness for the
+    /// key generation from `seed` instead of taking it from the operating system.
+    ///
+    /// Each call to [`NodeKeyProvisioner::provision_into`] draws the randomness for
+    /// the provisioned node from a single RNG seeded with `seed`, so that a sequence
+    /// of provisionings yields distinct nodes, and repeating the sequence with the
+    /// same seed yields the same nodes. Clones of the provisioner share this RNG.
+    /// Only the TLS certificates differ between repetitions, since their validity
+    /// starts at the time of the key generation.
+    ///
+    /// This is intended for test deployments only: the keys of a node are as secret
+    /// as the `seed`.
+    pub fn with_rng_seed(mut self, seed: [u8; 32]) -> Self {
+        self.rng = Some(Arc::new(Mutex::new(ChaCha20Rng::from_seed(seed))));
+        self
+    }
+
+    pub fn with_options(mut self, options: NodeKeyGenerationOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Provisions the node keys into the configured crypto root.
+    ///
+    /// # Panics
+    ///  * if no crypto root was configured with [`NodeKeyProvisioner::with_crypto_root`].
+    ///  * in the cases listed for [`NodeKeyProvisioner::provision_into`].
+    ///
+    /// # Errors
+    /// * in the cases listed for [`NodeKeyProvisioner::provision_into`].
+    pub fn provision(&self) -> Result<ProvisionedNode, NodeKeyGenerationError> {
+        let crypto_root = self
+            .crypto_root
+            .as_ref()
+            .expect("no crypto root configured");
+        self.provision_into(crypto_root)
+    }
+
+    /// Provisions the node keys into `crypto_root`, which is created with the
+    /// [permissions required for storing crypto state](CryptoConfig::check_dir_has_required_permissions)
+    /// if it does not exist yet.
+    ///
+    /// If `crypto_root` already contains node keys, no keys are generated (see
+    /// [`generate_node_keys_once`](crate::generate_node_keys_once)).
+    ///
+    /// # Panics
+    ///  * if `crypto_root` cannot be created.
+    ///  * in the cases listed for [`generate_node_keys_once`](crate::generate_node_keys_once).
+    ///
+    /// # Errors
+    /// * in the cases listed for [`generate_node_keys_once`](crate::generate_node_keys_once).
+    pub fn provision_into(
+        &self,
+        crypto_root: &Path,
+    ) -> Result<ProvisionedNode, NodeKeyGenerationError> {
+        if matches!(fs::symlink_metadata(crypto_root), Err(e) if e.kind() == ErrorKind::NotFound) {
+            create_crypto_root(crypto_root);
+        }
+        let config = CryptoConfig::new(crypto_root.to_path_buf());
+        let node_pks = match &self.rng {
+            None => generate_node_keys_once_with_options(&config, None, self.options.clone())?,
+            Some(rng) => {
+                let node_rng = ChaCha20Rng::from_seed(rng.lock().expect("poisoned RNG lock").gen());
+                let (node_pks, _outcome) = generate_node_keys_once_with_csp_for_config(
+                    &config,
+                    self.options.clone(),
+                    |config, store_retry_policy| {
+                        Csp::new_with_in_replica_vault_and_rng(
+                            config,
+                            node_rng,
+                            None,
+                            Arc::new(CryptoMetrics::none()),
+                            store_retry_policy,
+                        )
+                    },
+                )?;
+                node_pks
+            }
+        };
+        let key_ids = key_ids_for_node_public_keys(&CurrentNodePublicKeys {
+            node_signing_public_key: Some(node_pks.node_signing_key().clone()),
+            committee_signing_public_key: Some(node_pks.committee_signing_key().clone()),
+            tls_certificate: Some(node_pks.tls_certificate().clone()),
+            dkg_dealing_encryption_public_key: Some(node_pks.dkg_dealing_encryption_key().clone()),
+            idkg_dealing_encryption_public_key: Some(
+                node_pks.idkg_dealing_encryption_key().clone(),
+            ),
+        })
+        .expect("validated node public keys must be well-formed");
+        Ok(ProvisionedNode {
+            node_id: node_pks.node_id(),
+            fingerprints: NodeKeyFingerprints::of(&node_pks),
+            node_pks,
+            key_ids,
+        })
+    }
+}
+
+/// The public outputs of provisioning a node's keys.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProvisionedNode {
+    pub node_id: NodeId,
+    pub node_pks: ValidNodePublicKeys,
+    pub key_ids: NodeKeyIds,
+    pub fingerprints: NodeKeyFingerprints,
+}
+
+/// SHA-256 fingerprints of a node's public keys, i.e., the hashes of the respective
+/// public key bytes and of the DER-encoded TLS certificate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NodeKeyFingerprints {
+    pub node_signing_key: [u8; 32],
+    pub committee_signing_key: [u8; 32],
+    pub tls_certificate: [u8; 32],
+    pub dkg_dealing_encryption_key: [u8; 32],
+    pub idkg_dealing_encryption_key: [u8; 32],
+}
+
+impl NodeKeyFingerprints {
+    pub fn of(node_pks: &ValidNodePublicKeys) -> Self {
+        NodeKeyFingerprints {
+            node_signing_key: Sha256::hash(&node_pks.node_signing_key().key_value),
+            committee_signing_key: Sha256::hash(&node_pks.committee_signing_key().key_value),
+            tls_certificate: Sha256::hash(&node_pks.tls_certificate().certificate_der),
+            dkg_dealing_encryption_key: Sha256::hash(
+                &node_pks.dkg_dealing_encryption_key().key_value,
+            ),
+            idkg_dealing_encryption_key: Sha256::hash(
+                &node_pks.idkg_dealing_encryption_key().key_value,
+            ),
+        }
+    }
+}
+
+fn create_crypto_root(crypto_root: &Path) {
+    fs::create_dir_all(crypto_root).unwrap_or_else(|e| {
+        panic!(
+            "failed to create crypto root {}: {}",
+            crypto_root.display(),
+            e
+        )
+    });
+    fs::set_permissions(crypto_root, Permissions::from_mode(0o750)).unwrap_or_else(|e| {
+        panic!(
+            "failed to set permissions of crypto root {}: {}",
+            crypto_root.display(),
+            e
+        )
+    });
+}
@@ -0,0 +1,117 @@
+//! Comparison of a node's local public keys with the ones registered in the registry.
+use crate::crypto_root::check_crypto_root;
+use crate::csp_for_config;
+use crate::missing_keys::crypto_error;
+use ic_config::crypto::CryptoConfig;
+use ic_crypto_internal_csp::api::CspPublicKeyStore;
+use ic_protobuf::crypto::v1::NodePublicKeys;
+use ic_protobuf::registry::crypto::v1::PublicKey as PublicKeyProto;
+use ic_types::crypto::CryptoResult;
+
+/// Result of comparing a local public key with the corresponding key in the registry.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum KeyComparison {
+    /// The local and the registry key are equal.
+    Agree,
+    /// The local and the registry key are both present, but differ.
+    Differ,
+    /// The key is only present in the registry.
+    MissingLocally,
+    /// The key is only present locally.
+    MissingInRegistry,
+    /// The key is neither present locally nor in the registry.
+    MissingInBoth,
+}
+
+/// Per key type comparison of a node's local public keys with the ones in the registry.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyDiff {
+    pub node_signing_key: KeyComparison,
+    pub committee_signing_key: KeyComparison,
+    pub tls_certificate: KeyComparison,
+    pub dkg_dealing_encryption_key: KeyComparison,
+    pub idkg_dealing_encryption_key: KeyComparison,
+}
+
+impl KeyDiff {
+    /// Returns `true` iff the local and the registry keys agree for all key types.
+    pub fn is_consistent(&self) -> bool {
+        [
+            self.node_signing_key,
+            self.committee_signing_key,
+            self.tls_certificate,
+            self.dkg_dealing_encryption_key,
+            self.idkg_dealing_encryption_key,
+        ]
+        .iter()
+        .all(|comparison| *comparison == KeyComparison::Agree)
+    }
+}
+
+/// Compares the public keys in the public key store at `config.crypto_root` with
+/// `registry_keys`, e.g., to detect drift before a node joins a subnet.
+///
+/// Public keys are compared ignoring their timestamps, since the timestamp of a key
+/// in the registry is not necessarily the one of the local key. For the iDKG dealing
+/// encryption key, the latest key of `registry_keys`, i.e., the last one of
+/// `idkg_dealing_encryption_pks`, is compared with the node's current local key.
+///
+/// # Errors
+/// * [`CryptoError::InvalidArgument`](ic_types::crypto::CryptoError::InvalidArgument)
+///   if the crypto root is misconfigured.
+/// * [`CryptoError::TransientInternalError`](ic_types::crypto::CryptoError::TransientInternalError)
+///   if the public key store cannot be read, e.g., because of an RPC error communicating
+///   with the remote vault.
+pub fn diff_against_registry(
+    config: &CryptoConfig,
+    tokio_runtime_handle: Option<tokio::runtime::Handle>,
+    registry_keys: &NodePublicKeys,
+) -> CryptoResult<KeyDiff> {
+    check_crypto_root(&config.crypto_root, false).map_err(crypto_error)?;
+    let csp = csp_for_config(config, tokio_runtime_handle);
+    let local_keys = csp.current_node_public_keys()?;
+    Ok(KeyDiff {
+        node_signing_key: compare_public_keys(
+            local_keys.node_signing_public_key.as_ref(),
+            registry_keys.node_signing_pk.as_ref(),
+        ),
+        committee_signing_key: compare_public_keys(
+            local_keys.committee_signing_public_key.as_ref(),
+            registry_keys.committee_signing_pk.as_ref(),
+        ),
+        tls_certificate: compare(
+            local_keys.tls_certificate.as_ref(),
+            registry_keys.tls_certificate.as_ref(),
+            |local, registry| local.certificate_der == registry.certificate_der,
+        ),
+        dkg_dealing_encryption_key: compare_public_keys(
+            local_keys.dkg_dealing_encryption_public_key.as_ref(),
+            registry_keys.dkg_dealing_encryption_pk.as_ref(),
+        ),
+        idkg_dealing_encryption_key: compare_public_keys(
+            local_keys.idkg_dealing_encryption_public_key.as_ref(),
+            registry_keys.idkg_dealing_encryption_pks.last(),
+        ),
+    })
+}
+
+fn compare_public_keys(
+    local: Option<&PublicKeyProto>,
+    registry: Option<&PublicKeyProto>,
+) -> KeyComparison {
+    compare(local, registry, PublicKeyProto::equal_ignoring_timestamp)
+}
+
+fn compare<T, F: Fn(&T, &T) -> bool>(
+    local: Option<&T>,
+    registry: Option<&T>,
+    equal: F,
+) -> KeyComparison {
+    match (local, registry) {
+        (Some(local), Some(registry)) if equal(local, registry) => KeyComparison::Agree,
+        (Some(_), Some(_)) => KeyComparison::Differ,
+        (None, Some(_)) => KeyComparison::MissingLocally,
+        (Some(_), None) => KeyComparison::MissingInRegistry,
+        (None, None) => KeyComparison::MissingInBoth,
+    }
+}
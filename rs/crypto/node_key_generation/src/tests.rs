@@ -17,8 +17,6 @@ use ic_protobuf::registry::crypto::v1::PublicKey;
 use ic_types::crypto::CurrentNodePublicKeys;
 use ic_types_test_utils::ids::node_test_id;
 
-const RFC5280_NO_WELL_DEFINED_CERTIFICATE_EXPIRATION_DATE: &str = "99991231235959Z";
-
 mod generate_node_signing_keys {
     use super::*;
 
@@ -161,9 +159,12 @@ mod generate_required_node_keys_once_internal {
             .times(1)
             .return_const(Ok(expected_keys.clone()));
 
-        let result = generate_node_keys_once_internal(&csp);
+        let result = generate_node_keys_once_internal(&csp, &no_op_logger(), None);
 
-        assert_eq!(result, Ok(expected_keys));
+        assert_eq!(
+            result,
+            Ok((expected_keys, NodeKeyProvisioningOutcome::ReusedExisting))
+        );
     }
 
     #[test]
@@ -173,20 +174,50 @@ mod generate_required_node_keys_once_internal {
             ValidatePksAndSksError::TransientInternalError("RPC fails".to_string()),
         ));
 
-        let result = generate_node_keys_once_internal(&csp);
+        let result = generate_node_keys_once_internal(&csp, &no_op_logger(), None);
 
         assert_matches!(result, Err( NodeKeyGenerationError::TransientInternalError(e)) if e == "RPC fails");
     }
 
     #[test]
-    #[should_panic]
+    #[should_panic(expected = "NodeSigningKeyError(PublicKeyNotFound)")]
     fn should_panic_on_any_inconsistent_key_store_error() {
         let mut csp = MockAllCryptoServiceProvider::new();
         csp.expect_validate_pks_and_sks().times(1).return_const(Err(
             ValidatePksAndSksError::NodeSigningKeyError(PublicKeyNotFound),
         ));
+        csp.expect_current_node_public_keys()
+            .times(1)
+            .return_const(Ok(CurrentNodePublicKeys {
+                node_signing_public_key: None,
+                committee_signing_public_key: Some(valid_committee_signing_public_key()),
+                tls_certificate: Some(valid_tls_certificate().to_proto()),
+                dkg_dealing_encryption_public_key: None,
+                idkg_dealing_encryption_public_key: None,
+            }));
+
+        let _result = generate_node_keys_once_internal(&csp, &no_op_logger(), None);
+    }
+
+    #[test]
+    fn should_return_error_if_only_tls_certificate_exists() {
+        let mut csp = MockAllCryptoServiceProvider::new();
+        csp.expect_validate_pks_and_sks().times(1).return_const(Err(
+            ValidatePksAndSksError::NodeSigningKeyError(PublicKeyNotFound),
+        ));
+        csp.expect_current_node_public_keys()
+            .times(1)
+            .return_const(Ok(CurrentNodePublicKeys {
+                node_signing_public_key: None,
+                committee_signing_public_key: None,
+                tls_certificate: Some(valid_tls_certificate().to_proto()),
+                dkg_dealing_encryption_public_key: None,
+                idkg_dealing_encryption_public_key: None,
+            }));
+
+        let result = generate_node_keys_once_internal(&csp, &no_op_logger(), None);
 
-        let _result = generate_node_keys_once_internal(&csp);
+        assert_eq!(result, Err(NodeKeyGenerationError::TlsKeyMaterialOnly));
     }
 
     #[test]
@@ -199,9 +230,15 @@ mod generate_required_node_keys_once_internal {
             Ok(valid_node_public_keys.clone()),
         );
 
-        let result = generate_node_keys_once_internal(&csp);
+        let result = generate_node_keys_once_internal(&csp, &no_op_logger(), None);
 
-        assert_eq!(result, Ok(valid_node_public_keys));
+        assert_eq!(
+            result,
+            Ok((
+                valid_node_public_keys,
+                NodeKeyProvisioningOutcome::GeneratedAll
+            ))
+        );
     }
 
     #[test]
@@ -215,7 +252,7 @@ mod generate_required_node_keys_once_internal {
             Err(ValidatePksAndSksError::EmptyPublicKeyStore),
         );
 
-        let _result = generate_node_keys_once_internal(&csp);
+        let _result = generate_node_keys_once_internal(&csp, &no_op_logger(), None);
     }
 
     #[test]
@@ -231,7 +268,7 @@ mod generate_required_node_keys_once_internal {
             )),
         );
 
-        let _result = generate_node_keys_once_internal(&csp);
+        let _result = generate_node_keys_once_internal(&csp, &no_op_logger(), None);
     }
 
     #[test]
@@ -246,12 +283,516 @@ mod generate_required_node_keys_once_internal {
             )),
         );
 
-        let result = generate_node_keys_once_internal(&csp);
+        let result = generate_node_keys_once_internal(&csp, &no_op_logger(), None);
 
         assert_matches!(result, Err( NodeKeyGenerationError::TransientInternalError(e)) if e == "RPC fails");
     }
 }
 
+mod generate_all_node_keys {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn should_return_store_failed_error_without_regenerating_key_for_in_replica_vault() {
+        let mut csp = MockAllCryptoServiceProvider::new();
+        csp.expect_validate_pks_and_sks()
+            .times(1)
+            .return_const(Err(ValidatePksAndSksError::EmptyPublicKeyStore));
+        csp.expect_gen_node_signing_key_pair()
+            .times(1)
+            .return_const(Err(CspBasicSignatureKeygenError::TransientInternalError {
+                internal_error: "disk busy".to_string(),
+            }));
+
+        let result =
+            generate_node_keys_once_internal(&csp, &no_op_logger(), Some(&retry_policy(3)));
+
+        assert_eq!(
+            result,
+            Err(NodeKeyGenerationError::StoreFailed {
+                attempts: 3,
+                internal_error: "disk busy".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn should_not_generate_remaining_keys_after_store_failed_error() {
+        let mut csp = MockAllCryptoServiceProvider::new();
+        csp.expect_validate_pks_and_sks()
+            .times(1)
+            .return_const(Err(ValidatePksAndSksError::EmptyPublicKeyStore));
+        let _node_signing_pk = with_csp_gen_node_signing_key_pair(&mut csp);
+        csp.expect_gen_committee_signing_key_pair()
+            .times(1)
+            .return_const(Err(CspMultiSignatureKeygenError::TransientInternalError {
+                internal_error: "disk busy".to_string(),
+            }));
+        csp.expect_gen_tls_key_pair().times(0);
+        csp.expect_gen_dealing_encryption_key_pair().times(0);
+        csp.expect_idkg_gen_dealing_encryption_key_pair().times(0);
+
+        let result =
+            generate_node_keys_once_internal(&csp, &no_op_logger(), Some(&retry_policy(2)));
+
+        assert_eq!(
+            result,
+            Err(NodeKeyGenerationError::StoreFailed {
+                attempts: 2,
+                internal_error: "disk busy".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn should_return_transient_error_without_regenerating_key_for_remote_vault() {
+        let mut csp = MockAllCryptoServiceProvider::new();
+        csp.expect_validate_pks_and_sks()
+            .times(1)
+            .return_const(Err(ValidatePksAndSksError::EmptyPublicKeyStore));
+        csp.expect_gen_node_signing_key_pair()
+            .times(1)
+            .return_const(Err(CspBasicSignatureKeygenError::TransientInternalError {
+                internal_error: "RPC fails".to_string(),
+            }));
+
+        let result = generate_node_keys_once_internal(&csp, &no_op_logger(), None);
+
+        assert_eq!(
+            result,
+            Err(NodeKeyGenerationError::TransientInternalError(
+                "RPC fails".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Could not generate node signing keys")]
+    fn should_panic_on_non_transient_errors() {
+        let mut csp = MockAllCryptoServiceProvider::new();
+        csp.expect_validate_pks_and_sks()
+            .times(1)
+            .return_const(Err(ValidatePksAndSksError::EmptyPublicKeyStore));
+        csp.expect_gen_node_signing_key_pair()
+            .times(1)
+            .return_const(Err(CspBasicSignatureKeygenError::InternalError {
+                internal_error: "bad randomness".to_string(),
+            }));
+
+        let _result =
+            generate_node_keys_once_internal(&csp, &no_op_logger(), Some(&retry_policy(3)));
+    }
+
+    fn retry_policy(max_attempts: u32) -> StoreRetryPolicy {
+        StoreRetryPolicy {
+            max_attempts,
+            initial_backoff: Duration::ZERO,
+            max_backoff: Duration::ZERO,
+        }
+    }
+}
+
+mod generate_node_keys_once {
+    use super::*;
+
+    #[test]
+    fn should_return_locked_error_if_crypto_root_is_locked() {
+        CryptoConfig::run_with_temp_config(|config| {
+            let _lock = CryptoRootLock::try_acquire(&config.crypto_root)
+                .expect("failed to acquire crypto root lock");
+
+            let result = std::thread::spawn(move || generate_node_keys_once(&config, None))
+                .join()
+                .expect("key generation thread panicked");
+
+            assert_matches!(result, Err(NodeKeyGenerationError::Locked(_)));
+        })
+    }
+
+    #[test]
+    fn should_generate_keys_once_lock_is_released() {
+        CryptoConfig::run_with_temp_config(|config| {
+            let lock = CryptoRootLock::try_acquire(&config.crypto_root)
+                .expect("failed to acquire crypto root lock");
+            assert_matches!(
+                generate_node_keys_once(&config, None),
+                Err(NodeKeyGenerationError::Locked(_))
+            );
+
+            drop(lock);
+
+            assert_matches!(generate_node_keys_once(&config, None), Ok(_));
+        })
+    }
+
+    #[test]
+    fn should_release_lock_after_generating_keys() {
+        CryptoConfig::run_with_temp_config(|config| {
+            let _node_pks =
+                generate_node_keys_once(&config, None).expect("error generating node keys");
+
+            assert_matches!(CryptoRootLock::try_acquire(&config.crypto_root), Ok(_));
+        })
+    }
+}
+
+mod ensure_all_keys_internal {
+    use super::*;
+    use crate::missing_keys::ensure_all_keys_internal;
+    use ic_types::crypto::CryptoError;
+
+    #[test]
+    fn should_return_transient_error_if_generating_committee_signing_key_fails_transiently() {
+        let mut csp = MockAllCryptoServiceProvider::new();
+        with_csp_storing_only_node_signing_public_key(&mut csp);
+        csp.expect_gen_committee_signing_key_pair()
+            .times(1)
+            .return_const(Err(CspMultiSignatureKeygenError::TransientInternalError {
+                internal_error: "RPC fails".to_string(),
+            }));
+        csp.expect_gen_tls_key_pair().times(0);
+
+        let result = ensure_all_keys_internal(&csp, node_id_of_valid_node_signing_public_key());
+
+        assert_matches!(
+            result,
+            Err(CryptoError::TransientInternalError { internal_error }) if internal_error == "RPC fails"
+        );
+    }
+
+    #[test]
+    fn should_return_transient_error_if_generating_tls_key_fails_transiently() {
+        let mut csp = MockAllCryptoServiceProvider::new();
+        with_csp_storing_only_node_signing_public_key(&mut csp);
+        let _committee_signing_pk = with_csp_gen_committee_signing_key_pair(&mut csp);
+        csp.expect_gen_tls_key_pair().times(1).return_const(Err(
+            CspTlsKeygenError::TransientInternalError {
+                internal_error: "RPC fails".to_string(),
+            },
+        ));
+        csp.expect_gen_dealing_encryption_key_pair().times(0);
+
+        let result = ensure_all_keys_internal(&csp, node_id_of_valid_node_signing_public_key());
+
+        assert_matches!(
+            result,
+            Err(CryptoError::TransientInternalError { internal_error }) if internal_error == "RPC fails"
+        );
+    }
+
+    #[test]
+    fn should_return_transient_error_if_generating_dkg_dealing_encryption_key_fails_transiently() {
+        let mut csp = MockAllCryptoServiceProvider::new();
+        let node_id = node_id_of_valid_node_signing_public_key();
+        csp.expect_current_node_public_keys()
+            .times(1)
+            .return_const(Ok(CurrentNodePublicKeys {
+                node_signing_public_key: Some(valid_node_signing_public_key()),
+                committee_signing_public_key: Some(valid_committee_signing_public_key()),
+                tls_certificate: Some(valid_tls_certificate().to_proto()),
+                dkg_dealing_encryption_public_key: None,
+                idkg_dealing_encryption_public_key: Some(
+                    valid_idkg_dealing_encryption_public_key(),
+                ),
+            }));
+        csp.expect_gen_dealing_encryption_key_pair()
+            .times(1)
+            .return_const(Err(CspDkgCreateFsKeyError::TransientInternalError(
+                "RPC fails".to_string(),
+            )));
+
+        let result = ensure_all_keys_internal(&csp, node_id);
+
+        assert_matches!(
+            result,
+            Err(CryptoError::TransientInternalError { internal_error }) if internal_error == "RPC fails"
+        );
+    }
+
+    fn with_csp_storing_only_node_signing_public_key(csp: &mut MockAllCryptoServiceProvider) {
+        csp.expect_current_node_public_keys()
+            .times(1)
+            .return_const(Ok(CurrentNodePublicKeys {
+                node_signing_public_key: Some(valid_node_signing_public_key()),
+                committee_signing_public_key: None,
+                tls_certificate: None,
+                dkg_dealing_encryption_public_key: None,
+                idkg_dealing_encryption_public_key: None,
+            }));
+    }
+
+    fn node_id_of_valid_node_signing_public_key() -> NodeId {
+        *ValidNodeSigningPublicKey::try_from(valid_node_signing_public_key())
+            .expect("invalid node signing public key")
+            .derived_node_id()
+    }
+}
+
+mod crypto_root_lock {
+    use super::*;
+
+    #[test]
+    fn should_return_error_if_crypto_root_cannot_be_opened() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let crypto_root = temp_dir.path().join("missing");
+
+        assert_matches!(
+            CryptoRootLock::try_acquire(&crypto_root),
+            Err(NodeKeyGenerationError::CryptoRootLockFailed { crypto_root: path, .. })
+                if path == crypto_root
+        );
+    }
+
+    #[test]
+    fn should_lock_crypto_root_of_in_replica_vault() {
+        CryptoConfig::run_with_temp_config(|config| {
+            let lock = CryptoRootLock::try_acquire_for_in_replica_vault(&config)
+                .expect("failed to acquire crypto root lock");
+
+            assert!(lock.is_some());
+            assert_matches!(
+                CryptoRootLock::try_acquire(&config.crypto_root),
+                Err(NodeKeyGenerationError::Locked(_))
+            );
+        })
+    }
+
+    #[test]
+    fn should_not_lock_crypto_root_of_remote_vault() {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let config = CryptoConfig::new_with_unix_socket_vault(
+            temp_dir.path().join("unreadable"),
+            PathBuf::from("/run/ic-node/csp-vault.sock"),
+        );
+
+        assert_matches!(
+            CryptoRootLock::try_acquire_for_in_replica_vault(&config),
+            Ok(None)
+        );
+    }
+}
+
+mod generate_node_keys_once_with_options {
+    use super::*;
+    use ic_metrics::MetricsRegistry;
+    use ic_test_utilities_in_memory_logger::assertions::LogEntriesAssert;
+    use ic_test_utilities_in_memory_logger::InMemoryReplicaLogger;
+    use ic_test_utilities_metrics::fetch_int_counter_vec;
+    use slog::Level;
+    use std::os::unix::fs::symlink;
+    use tempfile::TempDir;
+
+    #[test]
+    fn should_return_not_found_error_if_crypto_root_does_not_exist() {
+        let temp_dir = TempDir::new().expect("failed to create temp dir");
+        let crypto_root = temp_dir.path().join("does_not_exist");
+
+        let result = generate_node_keys_once(&CryptoConfig::new(crypto_root.clone()), None);
+
+        assert_matches!(
+            result,
+            Err(NodeKeyGenerationError::CryptoRootNotFound { crypto_root: path }) if path == crypto_root
+        );
+    }
+
+    #[test]
+    fn should_return_not_a_directory_error_if_crypto_root_is_a_file() {
+        let temp_dir = TempDir::new().expect("failed to create temp dir");
+        let crypto_root = temp_dir.path().join("file");
+        std::fs::write(&crypto_root, b"not a directory").expect("failed to write file");
+
+        let result = generate_node_keys_once(&CryptoConfig::new(crypto_root.clone()), None);
+
+        assert_matches!(
+            result,
+            Err(NodeKeyGenerationError::CryptoRootNotADirectory { crypto_root: path }) if path == crypto_root
+        );
+    }
+
+    #[test]
+    fn should_return_symlink_error_if_crypto_root_is_a_symlink_by_default() {
+        CryptoConfig::run_with_temp_config(|config| {
+            let temp_dir = TempDir::new().expect("failed to create temp dir");
+            let link = temp_dir.path().join("link");
+            symlink(&config.crypto_root, &link).expect("failed to create symlink");
+
+            let result = generate_node_keys_once(&CryptoConfig::new(link.clone()), None);
+
+            assert_matches!(
+                result,
+                Err(NodeKeyGenerationError::CryptoRootIsSymlink { crypto_root, target })
+                    if crypto_root == link && target == config.crypto_root
+            );
+        })
+    }
+
+    #[test]
+    fn should_return_not_found_error_if_allowed_symlink_is_dangling() {
+        let temp_dir = TempDir::new().expect("failed to create temp dir");
+        let target = temp_dir.path().join("does_not_exist");
+        let link = temp_dir.path().join("link");
+        symlink(&target, &link).expect("failed to create symlink");
+
+        let result = generate_node_keys_once_with_options(
+            &CryptoConfig::new(link),
+            None,
+            NodeKeyGenerationOptions {
+                allow_symlinked_crypto_root: true,
+                ..NodeKeyGenerationOptions::default()
+            },
+        );
+
+        assert_matches!(
+            result,
+            Err(NodeKeyGenerationError::CryptoRootNotFound { crypto_root }) if crypto_root == target
+        );
+    }
+
+    #[test]
+    fn should_generate_keys_in_target_of_allowed_symlink() {
+        CryptoConfig::run_with_temp_config(|config| {
+            let temp_dir = TempDir::new().expect("failed to create temp dir");
+            let link = temp_dir.path().join("link");
+            symlink(&config.crypto_root, &link).expect("failed to create symlink");
+
+            let result = generate_node_keys_once_with_options(
+                &CryptoConfig::new(link),
+                None,
+                NodeKeyGenerationOptions {
+                    allow_symlinked_crypto_root: true,
+                    ..NodeKeyGenerationOptions::default()
+                },
+            );
+
+            assert_matches!(result, Ok(_));
+            assert!(config.crypto_root.join("public_keys.pb").exists());
+        })
+    }
+
+    #[test]
+    fn should_log_generation_of_new_node_keys() {
+        CryptoConfig::run_with_temp_config(|config| {
+            let in_memory_logger = InMemoryReplicaLogger::new();
+
+            let node_pks = generate_node_keys_once_with_options(
+                &config,
+                None,
+                NodeKeyGenerationOptions {
+                    logger: Some(ReplicaLogger::from(&in_memory_logger)),
+                    ..NodeKeyGenerationOptions::default()
+                },
+            )
+            .expect("error generating node keys");
+
+            let logs = in_memory_logger.drain_logs();
+            LogEntriesAssert::assert_that(logs)
+                .has_len(1)
+                .has_only_one_message_containing(
+                    &Level::Info,
+                    &format!("Generated new node keys for node {}", node_pks.node_id()),
+                );
+        })
+    }
+
+    #[test]
+    fn should_log_existing_node_keys() {
+        CryptoConfig::run_with_temp_config(|config| {
+            let node_pks =
+                generate_node_keys_once(&config, None).expect("error generating node keys");
+            let in_memory_logger = InMemoryReplicaLogger::new();
+
+            let _node_pks = generate_node_keys_once_with_options(
+                &config,
+                None,
+                NodeKeyGenerationOptions {
+                    logger: Some(ReplicaLogger::from(&in_memory_logger)),
+                    ..NodeKeyGenerationOptions::default()
+                },
+            )
+            .expect("error retrieving node keys");
+
+            let logs = in_memory_logger.drain_logs();
+            LogEntriesAssert::assert_that(logs)
+                .has_len(1)
+                .has_only_one_message_containing(
+                    &Level::Info,
+                    &format!("Found existing node keys for node {}", node_pks.node_id()),
+                );
+        })
+    }
+
+    #[test]
+    fn should_return_generated_all_outcome_for_empty_crypto_root() {
+        CryptoConfig::run_with_temp_config(|config| {
+            let (_node_pks, outcome) = generate_node_keys_once_with_outcome(
+                &config,
+                None,
+                NodeKeyGenerationOptions::default(),
+            )
+            .expect("error generating node keys");
+
+            assert_eq!(outcome, NodeKeyProvisioningOutcome::GeneratedAll);
+        })
+    }
+
+    #[test]
+    fn should_return_reused_existing_outcome_for_provisioned_crypto_root() {
+        CryptoConfig::run_with_temp_config(|config| {
+            let node_pks =
+                generate_node_keys_once(&config, None).expect("error generating node keys");
+
+            let (existing_node_pks, outcome) = generate_node_keys_once_with_outcome(
+                &config,
+                None,
+                NodeKeyGenerationOptions::default(),
+            )
+            .expect("error retrieving node keys");
+
+            assert_eq!(outcome, NodeKeyProvisioningOutcome::ReusedExisting);
+            assert_eq!(existing_node_pks, node_pks);
+        })
+    }
+
+    #[test]
+    fn should_record_outcomes_in_metrics() {
+        CryptoConfig::run_with_temp_config(|config| {
+            let registry = MetricsRegistry::new();
+            let options = NodeKeyGenerationOptions {
+                metrics: Some(Arc::new(CryptoMetrics::new(Some(&registry)))),
+                ..NodeKeyGenerationOptions::default()
+            };
+
+            for _ in 0..3 {
+                generate_node_keys_once_with_options(&config, None, options.clone())
+                    .expect("error generating node keys");
+            }
+
+            let outcomes =
+                fetch_int_counter_vec(&registry, "crypto_node_key_provisioning_outcomes");
+            let count_for = |outcome: NodeKeyProvisioningOutcome| {
+                outcomes
+                    .iter()
+                    .find(|(labels, _count)| labels.get("outcome") == Some(&format!("{}", outcome)))
+                    .map(|(_labels, count)| *count)
+            };
+            assert_eq!(count_for(NodeKeyProvisioningOutcome::GeneratedAll), Some(1));
+            assert_eq!(
+                count_for(NodeKeyProvisioningOutcome::ReusedExisting),
+                Some(2)
+            );
+        })
+    }
+
+    #[test]
+    fn should_treat_misconfigured_crypto_root_as_reproducible() {
+        assert!(NodeKeyGenerationError::CryptoRootNotFound {
+            crypto_root: PathBuf::from("/does/not/exist"),
+        }
+        .is_reproducible());
+    }
+}
+
 fn with_validate_pks_and_sks_returning(
     csp: &mut MockAllCryptoServiceProvider,
     result_on_first_call: Result<ValidNodePublicKeys, ValidatePksAndSksError>,
@@ -392,3 +933,113 @@ fn valid_tls_certificate() -> TlsPublicKeyCert {
     TlsPublicKeyCert::try_from(ic_crypto_test_utils_keys::public_keys::valid_tls_certificate())
         .expect("invalid TLS certificate")
 }
+
+mod reset_node_identity {
+    use super::*;
+    use crate::node_identity::remove_node_key_material;
+    use ic_crypto_internal_csp::vault::local_csp_vault::PUBLIC_KEY_STORE_DATA_FILENAME;
+    use ic_metrics::MetricsRegistry;
+    use ic_test_utilities_in_memory_logger::assertions::LogEntriesAssert;
+    use ic_test_utilities_in_memory_logger::InMemoryReplicaLogger;
+    use ic_test_utilities_metrics::fetch_int_counter_vec;
+    use slog::Level;
+
+    #[test]
+    fn should_log_generation_of_new_node_keys_with_logger_from_options() {
+        CryptoConfig::run_with_temp_config(|config| {
+            let old_node_pks =
+                generate_node_keys_once(&config, None).expect("error generating node keys");
+            let in_memory_logger = InMemoryReplicaLogger::new();
+
+            let new_node_pks = reset_node_identity(
+                &config,
+                None,
+                old_node_pks.node_id(),
+                NodeKeyGenerationOptions {
+                    logger: Some(ReplicaLogger::from(&in_memory_logger)),
+                    ..NodeKeyGenerationOptions::default()
+                },
+            )
+            .expect("error resetting node identity");
+
+            let logs = in_memory_logger.drain_logs();
+            LogEntriesAssert::assert_that(logs)
+                .has_len(1)
+                .has_only_one_message_containing(
+                    &Level::Info,
+                    &format!(
+                        "Generated new node keys for node {}",
+                        new_node_pks.node_id()
+                    ),
+                );
+        })
+    }
+
+    #[test]
+    fn should_record_outcome_in_metrics_from_options() {
+        CryptoConfig::run_with_temp_config(|config| {
+            let old_node_pks =
+                generate_node_keys_once(&config, None).expect("error generating node keys");
+            let registry = MetricsRegistry::new();
+
+            let _new_node_pks = reset_node_identity(
+                &config,
+                None,
+                old_node_pks.node_id(),
+                NodeKeyGenerationOptions {
+                    metrics: Some(Arc::new(CryptoMetrics::new(Some(&registry)))),
+                    ..NodeKeyGenerationOptions::default()
+                },
+            )
+            .expect("error resetting node identity");
+
+            let outcomes =
+                fetch_int_counter_vec(&registry, "crypto_node_key_provisioning_outcomes");
+            assert_eq!(outcomes.len(), 1);
+            assert!(outcomes.iter().any(|(labels, count)| {
+                labels.get("outcome")
+                    == Some(&format!("{}", NodeKeyProvisioningOutcome::GeneratedAll))
+                    && *count == 1
+            }));
+        })
+    }
+
+    #[test]
+    fn should_leave_clean_crypto_root_if_interrupted_after_removing_key_material() {
+        CryptoConfig::run_with_temp_config(|config| {
+            let old_node_pks =
+                generate_node_keys_once(&config, None).expect("error generating node keys");
+
+            // simulate a reset interrupted after removing all key material, but
+            // before generating the new keys
+            remove_node_key_material(&config.crypto_root).expect("error removing key material");
+
+            assert!(!config
+                .crypto_root
+                .join(PUBLIC_KEY_STORE_DATA_FILENAME)
+                .exists());
+            let new_node_pks =
+                generate_node_keys_once(&config, None).expect("error generating node keys");
+            assert_ne!(new_node_pks.node_id(), old_node_pks.node_id());
+            assert_eq!(
+                generate_node_keys_once(&config, None).expect("error retrieving node keys"),
+                new_node_pks
+            );
+        })
+    }
+
+    #[test]
+    fn should_remove_all_secret_keys_of_the_old_node() {
+        CryptoConfig::run_with_temp_config(|config| {
+            let _node_pks =
+                generate_node_keys_once(&config, None).expect("error generating node keys");
+
+            remove_node_key_material(&config.crypto_root).expect("error removing key material");
+
+            assert_matches!(
+                crate::node_identity::remove_all_secret_keys(&config.crypto_root),
+                Ok(0)
+            );
+        })
+    }
+}
@@ -0,0 +1,361 @@
+//! Utilities for generating and inspecting a node's TLS certificate.
+use crate::crypto_root::check_crypto_root;
+use crate::crypto_root_lock::CryptoRootLock;
+use crate::{
+    csp_for_config, NodeKeyGenerationError, RFC5280_NO_WELL_DEFINED_CERTIFICATE_EXPIRATION_DATE,
+};
+use chrono::{DateTime, TimeZone, Utc};
+use ic_config::crypto::{CryptoConfig, CspVaultType};
+use ic_crypto_internal_csp::api::{CspKeyGenerator, CspPublicKeyStore};
+use ic_crypto_internal_csp::key_id::KeyId;
+use ic_crypto_internal_csp::public_key_store::proto_pubkey_store::ProtoPublicKeyStore;
+use ic_crypto_internal_csp::secret_key_store::proto_store::ProtoSecretKeyStore;
+use ic_crypto_internal_csp::secret_key_store::SecretKeyStore;
+use ic_crypto_internal_csp::vault::api::{CspPublicKeyStoreError, CspTlsKeygenError};
+use ic_crypto_internal_csp::vault::local_csp_vault::{
+    PUBLIC_KEY_STORE_DATA_FILENAME, SKS_DATA_FILENAME,
+};
+use ic_crypto_tls_interfaces::TlsPublicKeyCert;
+use ic_logger::replica_logger::no_op_logger;
+use ic_protobuf::registry::crypto::v1::X509PublicKeyCert;
+use ic_types::crypto::{AlgorithmId, CryptoError, CryptoResult};
+use ic_types::{NodeId, Time};
+use openssl::asn1::{Asn1Time, Asn1TimeRef};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+// Seconds since the UNIX epoch of 9999-12-31 23:59:59 UTC, i.e., the notAfter date
+// of certificates without a well-defined expiration date (see RFC 5280 section 4.1.2.5).
+const NO_WELL_DEFINED_EXPIRATION_DATE_SECS_SINCE_UNIX_EPOCH: i64 = 253_402_300_799;
+const NANOS_PER_SEC: i128 = 1_000_000_000;
+
+/// Generates TLS key material bound to `node_id` in the key stores at `config.crypto_root`,
+/// without generating (or requiring) any other node keys.
+///
+/// This is intended for services other than the replica that need a TLS identity of the
+/// same form as a node's. The certificate is created exactly as in
+/// [`generate_tls_keys`](crate::generate_tls_keys), i.e., its subject embeds `node_id`
+/// and it has no well-defined expiration date.
+///
+/// An existing certificate is never overwritten (see
+/// [`generate_tls_keys_for_node_id_with_options`] for replacing it). Note that the
+/// resulting key stores cannot be complemented by the other node keys, since
+/// [`generate_node_keys_once`](crate::generate_node_keys_once) derives the node ID from
+/// a newly generated node signing key. Hence, it returns
+/// [`NodeKeyGenerationError::TlsKeyMaterialOnly`] for such a crypto root.
+///
+/// # Panics
+///  * if an error occurs when generating the keys.
+///
+/// # Errors
+/// * [`TlsKeyGenerationError::CertificateAlreadyExists`] if the public key store already
+///   contains a TLS certificate.
+/// * [`TlsKeyGenerationError::KeyGenerationError`] if the crypto root is misconfigured,
+///   locked, or cannot be locked, or if a transient error occurs. The crypto root is only
+///   locked if `config` uses an in-replica vault.
+pub fn generate_tls_keys_for_node_id(
+    config: &CryptoConfig,
+    tokio_runtime_handle: Option<tokio::runtime::Handle>,
+    node_id: NodeId,
+) -> Result<TlsPublicKeyCert, TlsKeyGenerationError> {
+    generate_tls_keys_for_node_id_with_options(
+        config,
+        tokio_runtime_handle,
+        node_id,
+        TlsKeyGenerationOptions::default(),
+    )
+}
+
+/// Options for the generation of TLS key material with
+/// [`generate_tls_keys_for_node_id_with_options`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TlsKeyGenerationOptions {
+    /// Whether an existing TLS certificate and its secret key are replaced by newly
+    /// generated ones. Only supported for an in-replica vault, whose key stores can be
+    /// modified locally.
+    pub replace: bool,
+}
+
+/// Like [`generate_tls_keys_for_node_id`], but allows to replace an existing TLS
+/// certificate via `options`.
+///
+/// If `options.replace` is set and the public key store contains a TLS certificate,
+/// the corresponding secret key is removed from the secret key store first, and the
+/// certificate is removed from the public key store afterwards, before new TLS key
+/// material is generated. All other keys in the key stores are left untouched. Hence,
+/// if the call is interrupted, the replacement can be completed by calling this
+/// function again. The crypto root is locked for the duration of the call.
+///
+/// # Panics
+///  * if an error occurs when generating the keys.
+///
+/// # Errors
+/// * [`TlsKeyGenerationError::CertificateAlreadyExists`] if the public key store already
+///   contains a TLS certificate and `options.replace` is not set.
+/// * [`TlsKeyGenerationError::UnsupportedVaultType`] if `options.replace` is set and
+///   `config` uses a remote vault, whose key material cannot be removed locally.
+/// * [`TlsKeyGenerationError::KeyMaterialRemovalFailed`] if the existing TLS key
+///   material cannot be removed.
+/// * [`TlsKeyGenerationError::KeyGenerationError`] if the crypto root is misconfigured,
+///   locked, or cannot be locked, or if a transient error occurs. The crypto root is only
+///   locked if `config` uses an in-replica vault.
+pub fn generate_tls_keys_for_node_id_with_options(
+    config: &CryptoConfig,
+    tokio_runtime_handle: Option<tokio::runtime::Handle>,
+    node_id: NodeId,
+    options: TlsKeyGenerationOptions,
+) -> Result<TlsPublicKeyCert, TlsKeyGenerationError> {
+    if options.replace && config.csp_vault_type != CspVaultType::InReplica {
+        return Err(TlsKeyGenerationError::UnsupportedVaultType(
+            config.csp_vault_type.clone(),
+        ));
+    }
+    check_crypto_root(&config.crypto_root, false)?;
+    let _lock = CryptoRootLock::try_acquire_for_in_replica_vault(config)?;
+    if let Some(tls_certificate) =
+        current_tls_certificate_proto(config, tokio_runtime_handle.clone())?
+    {
+        if !options.replace {
+            return Err(TlsKeyGenerationError::CertificateAlreadyExists);
+        }
+        remove_tls_key_material(&config.crypto_root, tls_certificate)?;
+    }
+    // The CSP is only created now, since its vault would otherwise still hold the
+    // removed key material in memory.
+    let csp = csp_for_config(config, tokio_runtime_handle);
+    csp.gen_tls_key_pair(node_id, RFC5280_NO_WELL_DEFINED_CERTIFICATE_EXPIRATION_DATE)
+        .map_err(|e| match e {
+            CspTlsKeygenError::TransientInternalError { internal_error } => {
+                TlsKeyGenerationError::from(NodeKeyGenerationError::TransientInternalError(
+                    internal_error,
+                ))
+            }
+            _ => panic!("error generating TLS key pair: {:?}", e),
+        })
+}
+
+/// Errors returned by [`generate_tls_keys_for_node_id`] and
+/// [`generate_tls_keys_for_node_id_with_options`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TlsKeyGenerationError {
+    /// If the public key store already contains a TLS certificate
+    CertificateAlreadyExists,
+    /// If the TLS key material could not be generated
+    KeyGenerationError(NodeKeyGenerationError),
+    /// If the vault type does not allow replacing the TLS key material locally
+    UnsupportedVaultType(CspVaultType),
+    /// If the existing TLS key material could not be removed from a key store
+    KeyMaterialRemovalFailed {
+        path: PathBuf,
+        internal_error: String,
+    },
+}
+
+impl From<NodeKeyGenerationError> for TlsKeyGenerationError {
+    fn from(error: NodeKeyGenerationError) -> Self {
+        TlsKeyGenerationError::KeyGenerationError(error)
+    }
+}
+
+fn current_tls_certificate_proto(
+    config: &CryptoConfig,
+    tokio_runtime_handle: Option<tokio::runtime::Handle>,
+) -> Result<Option<X509PublicKeyCert>, NodeKeyGenerationError> {
+    let csp = csp_for_config(config, tokio_runtime_handle);
+    let current_node_public_keys = csp.current_node_public_keys().map_err(
+        |CspPublicKeyStoreError::TransientInternalError(e)| {
+            NodeKeyGenerationError::TransientInternalError(e)
+        },
+    )?;
+    Ok(current_node_public_keys.tls_certificate)
+}
+
+// Removes the TLS secret key corresponding to `tls_certificate` from the node secret
+// key store at `crypto_root`, and then `tls_certificate` from the public key store
+// (see `generate_tls_keys_for_node_id_with_options`).
+fn remove_tls_key_material(
+    crypto_root: &Path,
+    tls_certificate: X509PublicKeyCert,
+) -> Result<(), TlsKeyGenerationError> {
+    let mut secret_key_store = ProtoSecretKeyStore::open(crypto_root, SKS_DATA_FILENAME, None);
+    let removal_failed =
+        |path: &Path, internal_error: String| TlsKeyGenerationError::KeyMaterialRemovalFailed {
+            path: path.to_path_buf(),
+            internal_error,
+        };
+    let key_id = TlsPublicKeyCert::try_from(tls_certificate)
+        .map_err(|e| e.internal_error)
+        .and_then(|cert| KeyId::try_from(&cert).map_err(|e| format!("{:?}", e)))
+        .map_err(|e| {
+            removal_failed(
+                secret_key_store.proto_file_path(),
+                format!(
+                    "failed to determine the key ID of the TLS certificate: {}",
+                    e
+                ),
+            )
+        })?;
+    secret_key_store
+        .remove(&key_id)
+        .map_err(|e| removal_failed(secret_key_store.proto_file_path(), format!("{:?}", e)))?;
+    let mut public_key_store =
+        ProtoPublicKeyStore::open(crypto_root, PUBLIC_KEY_STORE_DATA_FILENAME, no_op_logger());
+    public_key_store
+        .remove_tls_certificate()
+        .map_err(|e| removal_failed(public_key_store.proto_file_path(), e.to_string()))?;
+    Ok(())
+}
+
+/// Returns the validity window, i.e., the notBefore and notAfter dates, of the node's
+/// TLS certificate stored in the public key store at `config.crypto_root`.
+///
+/// Note that for certificates without a well-defined expiration date (see
+/// [`generate_tls_keys`](crate::generate_tls_keys)), the notAfter date is
+/// 9999-12-31 23:59:59 UTC.
+///
+/// # Errors
+/// * [`CryptoError::InternalError`] if the public key store does not contain a TLS certificate.
+/// * [`CryptoError::MalformedPublicKey`] if the TLS certificate cannot be parsed.
+/// * [`CryptoError::TransientInternalError`] if a transient internal error occurs, e.g.,
+///   an RPC error communicating with the remote vault.
+pub fn tls_cert_validity(
+    config: &CryptoConfig,
+    tokio_runtime_handle: Option<tokio::runtime::Handle>,
+) -> CryptoResult<(DateTime<Utc>, DateTime<Utc>)> {
+    let csp = csp_for_config(config, tokio_runtime_handle);
+    let tls_certificate = current_tls_certificate(&csp)?;
+    validity_window(&tls_certificate)
+}
+
+/// Returns the node's TLS certificate stored in the public key store at
+/// `config.crypto_root` in PEM format, i.e., the DER encoding of the certificate
+/// framed by `-----BEGIN CERTIFICATE-----` and `-----END CERTIFICATE-----`.
+///
+/// # Errors
+/// * [`CryptoError::InternalError`] if the public key store does not contain a TLS
+///   certificate, or if the certificate cannot be encoded in PEM format.
+/// * [`CryptoError::MalformedPublicKey`] if the TLS certificate cannot be parsed.
+/// * [`CryptoError::TransientInternalError`] if a transient internal error occurs, e.g.,
+///   an RPC error communicating with the remote vault.
+pub fn export_tls_cert_pem(
+    config: &CryptoConfig,
+    tokio_runtime_handle: Option<tokio::runtime::Handle>,
+) -> CryptoResult<String> {
+    let csp = csp_for_config(config, tokio_runtime_handle);
+    let tls_certificate = current_tls_certificate(&csp)?;
+    let pem = tls_certificate
+        .as_x509()
+        .to_pem()
+        .map_err(|e| CryptoError::InternalError {
+            internal_error: format!("failed to encode TLS certificate as PEM: {}", e),
+        })?;
+    String::from_utf8(pem).map_err(|e| CryptoError::InternalError {
+        internal_error: format!("PEM-encoded TLS certificate is not valid UTF-8: {}", e),
+    })
+}
+
+/// Validity of a TLS certificate at a particular point in time.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CertValidity {
+    /// The certificate's notBefore date lies after the point in time.
+    NotYetValid { starts_in: Duration },
+    /// The point in time lies within the certificate's validity window (bounds
+    /// included). `expires_in` is `None` if the certificate has no well-defined
+    /// expiration date.
+    Valid { expires_in: Option<Duration> },
+    /// The certificate's notAfter date lies before the point in time.
+    Expired { since: Duration },
+    /// The certificate's validity window cannot be parsed.
+    Malformed { internal_error: String },
+}
+
+/// Checks the validity of `cert` at time `now`.
+///
+/// The notBefore and notAfter dates are inclusive (see RFC 5280 section 4.1.2.5).
+/// Since no clock is read, the result is deterministic, and callers such as health
+/// reporting or metrics collection are expected to inject the current time.
+pub fn check_tls_cert_validity(cert: &TlsPublicKeyCert, now: Time) -> CertValidity {
+    let x509_cert = cert.as_x509();
+    let (not_before, not_after) = match (
+        asn1_time_to_unix_secs(x509_cert.not_before()),
+        asn1_time_to_unix_secs(x509_cert.not_after()),
+    ) {
+        (Ok(not_before), Ok(not_after)) => (not_before, not_after),
+        (Err(internal_error), _) | (_, Err(internal_error)) => {
+            return CertValidity::Malformed { internal_error }
+        }
+    };
+    let now_nanos = i128::from(now.as_nanos_since_unix_epoch());
+    let not_before_nanos = i128::from(not_before) * NANOS_PER_SEC;
+    let not_after_nanos = i128::from(not_after) * NANOS_PER_SEC;
+    if now_nanos < not_before_nanos {
+        CertValidity::NotYetValid {
+            starts_in: duration_from_nanos(not_before_nanos - now_nanos),
+        }
+    } else if not_after == NO_WELL_DEFINED_EXPIRATION_DATE_SECS_SINCE_UNIX_EPOCH {
+        CertValidity::Valid { expires_in: None }
+    } else if now_nanos <= not_after_nanos {
+        CertValidity::Valid {
+            expires_in: Some(duration_from_nanos(not_after_nanos - now_nanos)),
+        }
+    } else {
+        CertValidity::Expired {
+            since: duration_from_nanos(now_nanos - not_after_nanos),
+        }
+    }
+}
+
+fn current_tls_certificate<T: CspPublicKeyStore>(csp: &T) -> CryptoResult<TlsPublicKeyCert> {
+    let tls_certificate_proto =
+        csp.current_node_public_keys()?
+            .tls_certificate
+            .ok_or_else(|| CryptoError::InternalError {
+                internal_error: "no TLS certificate found in the public key store".to_string(),
+            })?;
+    let certificate_der = tls_certificate_proto.certificate_der.clone();
+    TlsPublicKeyCert::try_from(tls_certificate_proto)
+        .map_err(|e| malformed_tls_certificate_error(certificate_der, e.internal_error))
+}
+
+fn validity_window(cert: &TlsPublicKeyCert) -> CryptoResult<(DateTime<Utc>, DateTime<Utc>)> {
+    let x509_cert = cert.as_x509();
+    let not_before = asn1_time_to_date_time(x509_cert.not_before())
+        .map_err(|e| malformed_tls_certificate_error(cert.as_der().clone(), e))?;
+    let not_after = asn1_time_to_date_time(x509_cert.not_after())
+        .map_err(|e| malformed_tls_certificate_error(cert.as_der().clone(), e))?;
+    Ok((not_before, not_after))
+}
+
+fn asn1_time_to_date_time(time: &Asn1TimeRef) -> Result<DateTime<Utc>, String> {
+    Utc.timestamp_opt(asn1_time_to_unix_secs(time)?, 0)
+        .single()
+        .ok_or_else(|| format!("ASN.1 time {} is out of range", time))
+}
+
+fn asn1_time_to_unix_secs(time: &Asn1TimeRef) -> Result<i64, String> {
+    let unix_epoch =
+        Asn1Time::from_unix(0).map_err(|e| format!("failed to create ASN.1 time: {}", e))?;
+    let since_unix_epoch = unix_epoch
+        .diff(time)
+        .map_err(|e| format!("failed to compare ASN.1 time {}: {}", time, e))?;
+    Ok(i64::from(since_unix_epoch.days) * 24 * 60 * 60 + i64::from(since_unix_epoch.secs))
+}
+
+fn duration_from_nanos(nanos: i128) -> Duration {
+    let nanos = u128::try_from(nanos).expect("nanos must be non-negative");
+    Duration::new(
+        u64::try_from(nanos / NANOS_PER_SEC as u128).expect("seconds must fit into u64"),
+        (nanos % NANOS_PER_SEC as u128) as u32,
+    )
+}
+
+fn malformed_tls_certificate_error(
+    certificate_der: Vec<u8>,
+    internal_error: String,
+) -> CryptoError {
+    CryptoError::MalformedPublicKey {
+        algorithm: AlgorithmId::Tls,
+        key_bytes: Some(certificate_der),
+        internal_error,
+    }
+}
@@ -1,15 +1,47 @@
+use assert_matches::assert_matches;
+use chrono::{DateTime, Utc};
 use ic_config::crypto::CryptoConfig;
 use ic_crypto::{CryptoComponent, CryptoComponentImpl};
-use ic_crypto_internal_csp::Csp;
+use ic_crypto_internal_csp::api::{
+    CspKeyGenerator, CspPublicAndSecretKeyStoreChecker, CspPublicKeyStore,
+};
+use ic_crypto_internal_csp::key_id::KeyId;
+use ic_crypto_internal_csp::vault::api::{PublicKeyStoreCspVault, SecretKeyStoreCspVault};
+use ic_crypto_internal_csp::{Csp, LocalCspVault};
 use ic_crypto_internal_csp_test_utils::remote_csp_vault::start_new_remote_csp_vault_server_in_temp_dir;
-use ic_crypto_node_key_generation::generate_node_keys_once;
+use ic_crypto_internal_logmon::metrics::CryptoMetrics;
+use ic_crypto_node_key_generation::{
+    check_tls_cert_validity, create_key_attestation, diff_against_registry, ensure_all_keys,
+    estimate_key_material_size, export_tls_cert_pem, generate_committee_signing_keys,
+    generate_dkg_dealing_encryption_keys, generate_idkg_dealing_encryption_keys,
+    generate_node_keys_once, generate_node_signing_keys, generate_tls_keys,
+    generate_tls_keys_for_node_id, generate_tls_keys_for_node_id_with_options,
+    key_ids_for_node_public_keys, reset_node_identity, tls_cert_validity, verify_key_attestation,
+    wipe_secret_keys, CertValidity, KeyComparison, KeyDiff, NodeIdentityResetError,
+    NodeKeyFingerprints, NodeKeyGenerationError, NodeKeyGenerationOptions, NodeKeyProvisioner,
+    ProvisionedNode, TlsKeyGenerationError, TlsKeyGenerationOptions,
+};
 use ic_crypto_node_key_validation::ValidNodePublicKeys;
+use ic_crypto_sha::Sha256;
+use ic_crypto_test_utils_keys::public_keys::valid_idkg_dealing_encryption_public_key;
+use ic_crypto_tls_interfaces::TlsPublicKeyCert;
+use ic_crypto_utils_basic_sig::conversions::derive_node_id;
 use ic_interfaces::crypto::KeyManager;
 use ic_logger::replica_logger::no_op_logger;
 use ic_metrics::MetricsRegistry;
+use ic_protobuf::crypto::v1::NodePublicKeys;
 use ic_registry_client_fake::FakeRegistryClient;
 use ic_registry_proto_data_provider::ProtoRegistryDataProvider;
+use ic_types::crypto::CryptoError;
+use ic_types::Time;
+use ic_types_test_utils::ids::node_test_id;
+use nix::fcntl::{flock, FlockArg};
+use openssl::nid::Nid;
+use openssl::x509::X509;
+use std::collections::BTreeSet;
+use std::os::unix::io::AsRawFd;
 use std::sync::Arc;
+use std::time::Duration;
 
 #[test]
 fn should_generate_all_keys_for_new_node() {
@@ -54,6 +86,22 @@ fn should_generate_all_keys_for_new_node_with_remote_csp_vault() {
     assert_eq!(generated_pks, retrieved_keys);
 }
 
+#[test]
+fn should_not_lock_crypto_root_when_generating_keys_with_remote_csp_vault() {
+    let tokio_rt = new_tokio_runtime();
+    let (temp_dir, socket_path) = start_new_remote_csp_vault_server_in_temp_dir(tokio_rt.handle());
+    let config =
+        CryptoConfig::new_with_unix_socket_vault(temp_dir.path().to_path_buf(), socket_path);
+    let crypto_root = std::fs::File::open(&config.crypto_root).expect("failed to open crypto root");
+    flock(crypto_root.as_raw_fd(), FlockArg::LockExclusiveNonblock)
+        .expect("failed to lock crypto root");
+
+    assert_matches!(
+        generate_node_keys_once(&config, Some(tokio_rt.handle().clone())),
+        Ok(_)
+    );
+}
+
 #[test]
 fn should_not_generate_new_keys_if_all_keys_are_present() {
     CryptoConfig::run_with_temp_config(|config| {
@@ -63,6 +111,1038 @@ fn should_not_generate_new_keys_if_all_keys_are_present() {
     })
 }
 
+#[test]
+fn should_estimate_key_material_size_close_to_actual_size_on_disk() {
+    CryptoConfig::run_with_temp_config(|config| {
+        let _node_pks =
+            generate_node_keys_once(&config, None).expect("error generating node public keys");
+        let estimate = estimate_key_material_size();
+
+        let public_key_store_size = file_size(&config.crypto_root.join("public_keys.pb"));
+        let secret_key_store_size = file_size(&config.crypto_root.join("sks_data.pb"));
+
+        assert_within_band(public_key_store_size, estimate.public_key_store_bytes());
+        assert_within_band(secret_key_store_size, estimate.secret_key_store_bytes());
+        assert_within_band(
+            public_key_store_size + secret_key_store_size,
+            estimate.total_bytes(),
+        );
+    })
+}
+
+#[test]
+fn should_estimate_key_material_size_of_each_key_type_close_to_actual_size_on_disk() {
+    CryptoConfig::run_with_temp_config(|config| {
+        let estimate = estimate_key_material_size();
+        let csp = Csp::new(&config, None, None, Arc::new(CryptoMetrics::none()));
+        let sizes_before = key_store_sizes(&config);
+
+        let node_signing_public_key = generate_node_signing_keys(&csp);
+        let sizes_after_node_signing = key_store_sizes(&config);
+        let node_id = derive_node_id(&node_signing_public_key).expect("invalid node signing key");
+        let _committee_signing_public_key = generate_committee_signing_keys(&csp);
+        let sizes_after_committee_signing = key_store_sizes(&config);
+        let _tls_certificate = generate_tls_keys(&csp, node_id);
+        let sizes_after_tls = key_store_sizes(&config);
+        let _dkg_dealing_encryption_public_key =
+            generate_dkg_dealing_encryption_keys(&csp, node_id);
+        let sizes_after_dkg = key_store_sizes(&config);
+        let _idkg_dealing_encryption_public_key =
+            generate_idkg_dealing_encryption_keys(&csp).expect("error generating iDKG keys");
+        let sizes_after_idkg = key_store_sizes(&config);
+
+        for (key_type, (before, after), estimate) in [
+            (
+                "node signing",
+                (sizes_before, sizes_after_node_signing),
+                estimate.node_signing,
+            ),
+            (
+                "committee signing",
+                (sizes_after_node_signing, sizes_after_committee_signing),
+                estimate.committee_signing,
+            ),
+            (
+                "TLS",
+                (sizes_after_committee_signing, sizes_after_tls),
+                estimate.tls,
+            ),
+            (
+                "NI-DKG dealing encryption",
+                (sizes_after_tls, sizes_after_dkg),
+                estimate.dkg_dealing_encryption,
+            ),
+            (
+                "iDKG dealing encryption",
+                (sizes_after_dkg, sizes_after_idkg),
+                estimate.idkg_dealing_encryption,
+            ),
+        ] {
+            assert_within_tight_band(key_type, after.0 - before.0, estimate.public_key_bytes);
+            assert_within_tight_band(key_type, after.1 - before.1, estimate.secret_key_bytes);
+        }
+    })
+}
+
+#[test]
+fn should_return_tls_cert_validity_window_with_custom_not_after() {
+    CryptoConfig::run_with_temp_config(|config| {
+        let csp = Csp::new(&config, None, None, Arc::new(CryptoMetrics::none()));
+        let _cert = csp
+            .gen_tls_key_pair(node_test_id(42), "20351231235959Z")
+            .expect("error generating TLS key pair");
+
+        let (not_before, not_after) =
+            tls_cert_validity(&config, None).expect("error retrieving TLS cert validity");
+
+        assert_eq!(not_after, utc_date_time("2035-12-31T23:59:59Z"));
+        assert!(not_before <= Utc::now());
+        assert!(not_before < not_after);
+    })
+}
+
+#[test]
+fn should_return_far_future_not_after_for_generated_node_keys() {
+    CryptoConfig::run_with_temp_config(|config| {
+        let _node_pks =
+            generate_node_keys_once(&config, None).expect("error generating node public keys");
+
+        let (_not_before, not_after) =
+            tls_cert_validity(&config, None).expect("error retrieving TLS cert validity");
+
+        assert_eq!(not_after, utc_date_time("9999-12-31T23:59:59Z"));
+    })
+}
+
+#[test]
+fn should_check_tls_cert_validity_at_and_around_window_bounds() {
+    CryptoConfig::run_with_temp_config(|config| {
+        let csp = Csp::new(&config, None, None, Arc::new(CryptoMetrics::none()));
+        let cert = csp
+            .gen_tls_key_pair(node_test_id(42), "20351231235959Z")
+            .expect("error generating TLS key pair");
+        let (not_before, not_after) =
+            tls_cert_validity(&config, None).expect("error retrieving TLS cert validity");
+        let one_sec = Duration::from_secs(1);
+        let window = Duration::from_secs((not_after - not_before).num_seconds() as u64);
+
+        assert_eq!(
+            check_tls_cert_validity(&cert, time(not_before) - one_sec),
+            CertValidity::NotYetValid { starts_in: one_sec }
+        );
+        assert_eq!(
+            check_tls_cert_validity(&cert, time(not_before)),
+            CertValidity::Valid {
+                expires_in: Some(window)
+            }
+        );
+        assert_eq!(
+            check_tls_cert_validity(&cert, time(not_after)),
+            CertValidity::Valid {
+                expires_in: Some(Duration::ZERO)
+            }
+        );
+        assert_eq!(
+            check_tls_cert_validity(&cert, time(not_after) + one_sec),
+            CertValidity::Expired { since: one_sec }
+        );
+    })
+}
+
+#[test]
+fn should_report_no_expiry_for_tls_cert_of_generated_node_keys() {
+    CryptoConfig::run_with_temp_config(|config| {
+        let node_pks =
+            generate_node_keys_once(&config, None).expect("error generating node public keys");
+        let cert = TlsPublicKeyCert::try_from(node_pks.tls_certificate().clone())
+            .expect("invalid TLS certificate");
+
+        assert_eq!(
+            check_tls_cert_validity(&cert, time(Utc::now())),
+            CertValidity::Valid { expires_in: None }
+        );
+    })
+}
+
+#[test]
+fn should_refuse_to_reset_node_identity_if_node_id_does_not_match() {
+    CryptoConfig::run_with_temp_config(|config| {
+        let node_pks =
+            generate_node_keys_once(&config, None).expect("error generating node public keys");
+        let wrong_node_id = node_test_id(1);
+        assert_ne!(node_pks.node_id(), wrong_node_id);
+
+        let result = reset_node_identity(
+            &config,
+            None,
+            wrong_node_id,
+            NodeKeyGenerationOptions::default(),
+        );
+
+        assert_eq!(
+            result,
+            Err(NodeIdentityResetError::NodeIdMismatch {
+                expected: wrong_node_id,
+                found: Some(node_pks.node_id()),
+            })
+        );
+        assert_eq!(
+            generate_node_keys_once(&config, None).expect("error retrieving node public keys"),
+            node_pks
+        );
+    })
+}
+
+#[test]
+fn should_reset_node_identity() {
+    CryptoConfig::run_with_temp_config(|config| {
+        let old_node_pks =
+            generate_node_keys_once(&config, None).expect("error generating node public keys");
+
+        let new_node_pks = reset_node_identity(
+            &config,
+            None,
+            old_node_pks.node_id(),
+            NodeKeyGenerationOptions::default(),
+        )
+        .expect("error resetting node identity");
+
+        assert_ne!(new_node_pks.node_id(), old_node_pks.node_id());
+        assert_eq!(
+            generate_node_keys_once(&config, None).expect("error retrieving node public keys"),
+            new_node_pks
+        );
+    })
+}
+
+#[test]
+fn should_complete_interrupted_node_identity_reset() {
+    CryptoConfig::run_with_temp_config(|config| {
+        let old_node_pks =
+            generate_node_keys_once(&config, None).expect("error generating node public keys");
+        // simulate a reset interrupted after removing the secret key store
+        std::fs::remove_file(config.crypto_root.join("sks_data.pb"))
+            .expect("error removing secret key store");
+
+        let new_node_pks = reset_node_identity(
+            &config,
+            None,
+            old_node_pks.node_id(),
+            NodeKeyGenerationOptions::default(),
+        )
+        .expect("error resetting node identity");
+
+        assert_ne!(new_node_pks.node_id(), old_node_pks.node_id());
+        assert_eq!(
+            generate_node_keys_once(&config, None).expect("error retrieving node public keys"),
+            new_node_pks
+        );
+    })
+}
+
+#[test]
+fn should_generate_only_tls_keys_for_node_id() {
+    CryptoConfig::run_with_temp_config(|config| {
+        let node_id = node_test_id(42);
+
+        let cert = generate_tls_keys_for_node_id(&config, None, node_id)
+            .expect("error generating TLS keys");
+
+        let common_name = cert
+            .as_x509()
+            .subject_name()
+            .entries_by_nid(Nid::COMMONNAME)
+            .next()
+            .expect("missing common name")
+            .data()
+            .as_utf8()
+            .expect("invalid common name")
+            .to_string();
+        assert_eq!(common_name, node_id.get().to_string());
+        let csp = Csp::new(&config, None, None, Arc::new(CryptoMetrics::none()));
+        let current_node_public_keys = csp
+            .current_node_public_keys()
+            .expect("error retrieving public keys");
+        assert_eq!(
+            current_node_public_keys.tls_certificate,
+            Some(cert.to_proto())
+        );
+        assert_eq!(current_node_public_keys.node_signing_public_key, None);
+        assert_eq!(current_node_public_keys.committee_signing_public_key, None);
+    })
+}
+
+#[test]
+fn should_refuse_to_overwrite_existing_tls_certificate() {
+    CryptoConfig::run_with_temp_config(|config| {
+        let cert = generate_tls_keys_for_node_id(&config, None, node_test_id(42))
+            .expect("error generating TLS keys");
+
+        assert_eq!(
+            generate_tls_keys_for_node_id(&config, None, node_test_id(43)),
+            Err(TlsKeyGenerationError::CertificateAlreadyExists)
+        );
+        let (_not_before, not_after) =
+            tls_cert_validity(&config, None).expect("error retrieving TLS cert validity");
+        assert_eq!(not_after, utc_date_time("9999-12-31T23:59:59Z"));
+        assert_eq!(
+            Csp::new(&config, None, None, Arc::new(CryptoMetrics::none()))
+                .current_node_public_keys()
+                .expect("error retrieving public keys")
+                .tls_certificate,
+            Some(cert.to_proto())
+        );
+    })
+}
+
+#[test]
+fn should_replace_existing_tls_certificate_and_secret_key() {
+    CryptoConfig::run_with_temp_config(|config| {
+        let old_cert = generate_tls_keys_for_node_id(&config, None, node_test_id(42))
+            .expect("error generating TLS keys");
+
+        let new_cert = generate_tls_keys_for_node_id_with_options(
+            &config,
+            None,
+            node_test_id(43),
+            TlsKeyGenerationOptions { replace: true },
+        )
+        .expect("error replacing TLS keys");
+
+        assert_ne!(new_cert, old_cert);
+        let vault = LocalCspVault::new_in_dir(
+            &config.crypto_root,
+            Arc::new(CryptoMetrics::none()),
+            no_op_logger(),
+        );
+        assert_eq!(
+            vault
+                .current_node_public_keys()
+                .expect("error retrieving public keys")
+                .tls_certificate,
+            Some(new_cert.to_proto())
+        );
+        let sks_contains = |cert: &TlsPublicKeyCert| {
+            vault
+                .sks_contains(&KeyId::try_from(cert).expect("error computing key ID"))
+                .expect("error querying secret key store")
+        };
+        assert!(!sks_contains(&old_cert));
+        assert!(sks_contains(&new_cert));
+    })
+}
+
+#[test]
+fn should_replace_only_tls_key_material_of_fully_provisioned_node() {
+    CryptoConfig::run_with_temp_config(|config| {
+        let node_pks =
+            generate_node_keys_once(&config, None).expect("error generating node public keys");
+
+        let new_cert = generate_tls_keys_for_node_id_with_options(
+            &config,
+            None,
+            node_pks.node_id(),
+            TlsKeyGenerationOptions { replace: true },
+        )
+        .expect("error replacing TLS keys");
+
+        assert_ne!(new_cert.to_proto(), node_pks.tls_certificate().clone());
+        let node_pks_after_replacement =
+            generate_node_keys_once(&config, None).expect("error retrieving node public keys");
+        assert_eq!(node_pks_after_replacement.node_id(), node_pks.node_id());
+        assert_eq!(
+            node_pks_after_replacement.node_signing_key(),
+            node_pks.node_signing_key()
+        );
+        assert_eq!(
+            node_pks_after_replacement.committee_signing_key(),
+            node_pks.committee_signing_key()
+        );
+        assert_eq!(
+            node_pks_after_replacement.dkg_dealing_encryption_key(),
+            node_pks.dkg_dealing_encryption_key()
+        );
+        assert_eq!(
+            node_pks_after_replacement.idkg_dealing_encryption_key(),
+            node_pks.idkg_dealing_encryption_key()
+        );
+        assert_eq!(
+            node_pks_after_replacement.tls_certificate(),
+            &new_cert.to_proto()
+        );
+    })
+}
+
+#[test]
+fn should_generate_tls_keys_with_replace_option_if_no_certificate_exists() {
+    CryptoConfig::run_with_temp_config(|config| {
+        let cert = generate_tls_keys_for_node_id_with_options(
+            &config,
+            None,
+            node_test_id(42),
+            TlsKeyGenerationOptions { replace: true },
+        )
+        .expect("error generating TLS keys");
+
+        assert_eq!(
+            Csp::new(&config, None, None, Arc::new(CryptoMetrics::none()))
+                .current_node_public_keys()
+                .expect("error retrieving public keys")
+                .tls_certificate,
+            Some(cert.to_proto())
+        );
+    })
+}
+
+#[test]
+fn should_refuse_to_replace_tls_certificate_of_remote_vault() {
+    let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+    let config = CryptoConfig::new_with_unix_socket_vault(
+        temp_dir.path().to_path_buf(),
+        temp_dir.path().join("vault.sock"),
+    );
+
+    let result = generate_tls_keys_for_node_id_with_options(
+        &config,
+        None,
+        node_test_id(42),
+        TlsKeyGenerationOptions { replace: true },
+    );
+
+    assert_eq!(
+        result,
+        Err(TlsKeyGenerationError::UnsupportedVaultType(
+            config.csp_vault_type.clone()
+        ))
+    );
+}
+
+#[test]
+fn should_refuse_to_generate_tls_keys_for_fully_provisioned_node() {
+    CryptoConfig::run_with_temp_config(|config| {
+        let node_pks =
+            generate_node_keys_once(&config, None).expect("error generating node public keys");
+
+        assert_eq!(
+            generate_tls_keys_for_node_id(&config, None, node_pks.node_id()),
+            Err(TlsKeyGenerationError::CertificateAlreadyExists)
+        );
+    })
+}
+
+#[test]
+fn should_refuse_to_generate_node_keys_after_generating_only_tls_keys() {
+    CryptoConfig::run_with_temp_config(|config| {
+        let _cert = generate_tls_keys_for_node_id(&config, None, node_test_id(42))
+            .expect("error generating TLS keys");
+
+        assert_eq!(
+            generate_node_keys_once(&config, None),
+            Err(NodeKeyGenerationError::TlsKeyMaterialOnly)
+        );
+    })
+}
+
+#[test]
+fn should_compute_key_ids_of_all_keys_in_secret_key_store() {
+    CryptoConfig::run_with_temp_config(|config| {
+        let _node_pks =
+            generate_node_keys_once(&config, None).expect("error generating node public keys");
+        let vault = LocalCspVault::new_in_dir(
+            &config.crypto_root,
+            Arc::new(CryptoMetrics::none()),
+            no_op_logger(),
+        );
+        let current_node_public_keys = vault
+            .current_node_public_keys()
+            .expect("error retrieving public keys");
+
+        let key_ids = key_ids_for_node_public_keys(&current_node_public_keys)
+            .expect("error computing key IDs");
+
+        for key_id in [
+            key_ids.node_signing_key_id,
+            key_ids.committee_signing_key_id,
+            key_ids.tls_secret_key_id,
+            key_ids.dkg_dealing_encryption_key_id,
+            key_ids.idkg_dealing_encryption_key_id,
+        ] {
+            let key_id = key_id.expect("missing key ID");
+            assert!(vault
+                .sks_contains(&key_id)
+                .expect("error querying secret key store"));
+        }
+    })
+}
+
+#[test]
+fn should_not_compute_key_ids_of_missing_public_keys() {
+    CryptoConfig::run_with_temp_config(|config| {
+        let _cert = generate_tls_keys_for_node_id(&config, None, node_test_id(42))
+            .expect("error generating TLS keys");
+        let current_node_public_keys =
+            Csp::new(&config, None, None, Arc::new(CryptoMetrics::none()))
+                .current_node_public_keys()
+                .expect("error retrieving public keys");
+
+        let key_ids = key_ids_for_node_public_keys(&current_node_public_keys)
+            .expect("error computing key IDs");
+
+        assert!(key_ids.tls_secret_key_id.is_some());
+        assert_eq!(key_ids.node_signing_key_id, None);
+        assert_eq!(key_ids.committee_signing_key_id, None);
+        assert_eq!(key_ids.dkg_dealing_encryption_key_id, None);
+        assert_eq!(key_ids.idkg_dealing_encryption_key_id, None);
+    })
+}
+
+#[test]
+fn should_provision_many_nodes_with_one_provisioner() {
+    let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+    let provisioner = NodeKeyProvisioner::new();
+
+    let provisioned_nodes: Vec<_> = (0..3)
+        .map(|i| {
+            let crypto_root = temp_dir.path().join(format!("node_{}", i));
+            let provisioned_node = provisioner
+                .provision_into(&crypto_root)
+                .expect("error provisioning node");
+            (crypto_root, provisioned_node)
+        })
+        .collect();
+
+    let node_ids: BTreeSet<_> = provisioned_nodes
+        .iter()
+        .map(|(_crypto_root, provisioned_node)| provisioned_node.node_id)
+        .collect();
+    assert_eq!(node_ids.len(), 3);
+    for (crypto_root, provisioned_node) in provisioned_nodes {
+        assert_eq!(
+            provisioned_node.node_pks.node_id(),
+            provisioned_node.node_id
+        );
+        assert_eq!(
+            provisioned_node.fingerprints,
+            NodeKeyFingerprints::of(&provisioned_node.node_pks)
+        );
+        CryptoConfig::check_dir_has_required_permissions(&crypto_root)
+            .expect("wrong crypto root permissions");
+        assert_eq!(
+            generate_node_keys_once(&CryptoConfig::new(crypto_root), None)
+                .expect("error retrieving node public keys"),
+            provisioned_node.node_pks
+        );
+    }
+}
+
+#[test]
+fn should_provision_deterministic_distinct_nodes_with_rng_seed() {
+    let provision_three_nodes = |seed: [u8; 32]| {
+        let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+        let provisioner = NodeKeyProvisioner::new().with_rng_seed(seed);
+        (0..3)
+            .map(|i| {
+                let crypto_root = temp_dir.path().join(format!("node_{}", i));
+                let provisioned_node = provisioner
+                    .provision_into(&crypto_root)
+                    .expect("error provisioning node");
+                assert_eq!(
+                    generate_node_keys_once(&CryptoConfig::new(crypto_root), None)
+                        .expect("error retrieving node public keys"),
+                    provisioned_node.node_pks
+                );
+                provisioned_node
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let nodes = provision_three_nodes([42; 32]);
+    let nodes_with_same_seed = provision_three_nodes([42; 32]);
+    let nodes_with_other_seed = provision_three_nodes([43; 32]);
+
+    let node_ids =
+        |nodes: &[ProvisionedNode]| -> Vec<_> { nodes.iter().map(|node| node.node_id).collect() };
+    assert_eq!(node_ids(&nodes).iter().collect::<BTreeSet<_>>().len(), 3);
+    assert_eq!(node_ids(&nodes), node_ids(&nodes_with_same_seed));
+    for (node, node_with_same_seed) in nodes.iter().zip(&nodes_with_same_seed) {
+        let (fingerprints, same_seed_fingerprints) =
+            (node.fingerprints, node_with_same_seed.fingerprints);
+        assert_eq!(
+            fingerprints.committee_signing_key,
+            same_seed_fingerprints.committee_signing_key
+        );
+        assert_eq!(
+            fingerprints.dkg_dealing_encryption_key,
+            same_seed_fingerprints.dkg_dealing_encryption_key
+        );
+        assert_eq!(
+            fingerprints.idkg_dealing_encryption_key,
+            same_seed_fingerprints.idkg_dealing_encryption_key
+        );
+    }
+    assert!(node_ids(&nodes_with_other_seed)
+        .iter()
+        .all(|node_id| !node_ids(&nodes).contains(node_id)));
+}
+
+#[test]
+fn should_provision_into_configured_crypto_root() {
+    CryptoConfig::run_with_temp_config(|config| {
+        let provisioned_node = NodeKeyProvisioner::new()
+            .with_crypto_root(config.crypto_root.clone())
+            .provision()
+            .expect("error provisioning node");
+
+        assert_eq!(
+            generate_node_keys_once(&config, None).expect("error retrieving node public keys"),
+            provisioned_node.node_pks
+        );
+    })
+}
+
+#[test]
+fn should_return_sha256_fingerprints_of_provisioned_keys() {
+    CryptoConfig::run_with_temp_config(|config| {
+        let provisioned_node = NodeKeyProvisioner::new()
+            .provision_into(&config.crypto_root)
+            .expect("error provisioning node");
+
+        let node_pks = &provisioned_node.node_pks;
+        let fingerprints = provisioned_node.fingerprints;
+        assert_eq!(
+            fingerprints.node_signing_key,
+            Sha256::hash(&node_pks.node_signing_key().key_value)
+        );
+        assert_eq!(
+            fingerprints.committee_signing_key,
+            Sha256::hash(&node_pks.committee_signing_key().key_value)
+        );
+        assert_eq!(
+            fingerprints.tls_certificate,
+            Sha256::hash(&node_pks.tls_certificate().certificate_der)
+        );
+        assert_eq!(
+            fingerprints.dkg_dealing_encryption_key,
+            Sha256::hash(&node_pks.dkg_dealing_encryption_key().key_value)
+        );
+        assert_eq!(
+            fingerprints.idkg_dealing_encryption_key,
+            Sha256::hash(&node_pks.idkg_dealing_encryption_key().key_value)
+        );
+    })
+}
+
+#[test]
+fn should_export_tls_cert_in_pem_format() {
+    CryptoConfig::run_with_temp_config(|config| {
+        let node_pks =
+            generate_node_keys_once(&config, None).expect("error generating node public keys");
+
+        let pem = export_tls_cert_pem(&config, None).expect("error exporting TLS cert");
+
+        assert!(pem.starts_with("-----BEGIN CERTIFICATE-----\n"));
+        assert!(pem.ends_with("-----END CERTIFICATE-----\n"));
+        let der = X509::from_pem(pem.as_bytes())
+            .expect("invalid PEM")
+            .to_der()
+            .expect("error encoding certificate as DER");
+        assert_eq!(der, node_pks.tls_certificate().certificate_der);
+    })
+}
+
+#[test]
+fn should_fail_to_export_tls_cert_in_pem_format_if_no_cert() {
+    CryptoConfig::run_with_temp_config(|config| {
+        let result = export_tls_cert_pem(&config, None);
+
+        assert_matches!(result, Err(CryptoError::InternalError { .. }));
+    })
+}
+
+#[test]
+fn should_agree_with_registry_keys_equal_to_local_keys() {
+    CryptoConfig::run_with_temp_config(|config| {
+        let node_pks =
+            generate_node_keys_once(&config, None).expect("error generating node public keys");
+
+        let diff = diff_against_registry(&config, None, &registry_keys(&node_pks))
+            .expect("error comparing keys");
+
+        assert!(diff.is_consistent());
+    })
+}
+
+#[test]
+fn should_flag_only_differing_idkg_dealing_encryption_key() {
+    CryptoConfig::run_with_temp_config(|config| {
+        let node_pks =
+            generate_node_keys_once(&config, None).expect("error generating node public keys");
+        let registry_keys = NodePublicKeys {
+            idkg_dealing_encryption_pks: vec![valid_idkg_dealing_encryption_public_key()],
+            ..registry_keys(&node_pks)
+        };
+
+        let diff =
+            diff_against_registry(&config, None, &registry_keys).expect("error comparing keys");
+
+        assert_eq!(
+            diff,
+            KeyDiff {
+                node_signing_key: KeyComparison::Agree,
+                committee_signing_key: KeyComparison::Agree,
+                tls_certificate: KeyComparison::Agree,
+                dkg_dealing_encryption_key: KeyComparison::Agree,
+                idkg_dealing_encryption_key: KeyComparison::Differ,
+            }
+        );
+        assert!(!diff.is_consistent());
+    })
+}
+
+#[test]
+fn should_flag_keys_missing_locally() {
+    CryptoConfig::run_with_temp_config(|config| {
+        let registry_keys = NodePublicKeys {
+            idkg_dealing_encryption_pks: vec![valid_idkg_dealing_encryption_public_key()],
+            ..NodePublicKeys::default()
+        };
+
+        let diff =
+            diff_against_registry(&config, None, &registry_keys).expect("error comparing keys");
+
+        assert_eq!(
+            diff.idkg_dealing_encryption_key,
+            KeyComparison::MissingLocally
+        );
+        assert_eq!(diff.node_signing_key, KeyComparison::MissingInBoth);
+    })
+}
+
+#[test]
+fn should_return_invalid_argument_when_comparing_keys_in_missing_crypto_root() {
+    let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+    let config = CryptoConfig::new(temp_dir.path().join("missing"));
+
+    let result = diff_against_registry(&config, None, &NodePublicKeys::default());
+
+    assert_matches!(result, Err(CryptoError::InvalidArgument { .. }));
+}
+
+#[test]
+fn should_generate_only_missing_committee_signing_key() {
+    CryptoConfig::run_with_temp_config(|config| {
+        let csp = Csp::new(&config, None, None, Arc::new(CryptoMetrics::none()));
+        let node_signing_public_key = generate_node_signing_keys(&csp);
+        let node_id = derive_node_id(&node_signing_public_key).expect("invalid node signing key");
+        let _tls_certificate = generate_tls_keys(&csp, node_id);
+        let _dkg_dealing_encryption_public_key =
+            generate_dkg_dealing_encryption_keys(&csp, node_id);
+        let _idkg_dealing_encryption_public_key =
+            generate_idkg_dealing_encryption_keys(&csp).expect("error generating iDKG keys");
+        let keys_before = csp
+            .current_node_public_keys()
+            .expect("error retrieving public keys");
+        assert_eq!(keys_before.committee_signing_public_key, None);
+
+        let node_pks = ensure_all_keys(&config, None, node_id).expect("error ensuring keys");
+
+        assert_eq!(node_pks.node_id(), node_id);
+        assert_eq!(
+            Some(node_pks.node_signing_key()),
+            keys_before.node_signing_public_key.as_ref()
+        );
+        assert_eq!(
+            Some(node_pks.tls_certificate()),
+            keys_before.tls_certificate.as_ref()
+        );
+        assert_eq!(
+            Some(node_pks.dkg_dealing_encryption_key()),
+            keys_before.dkg_dealing_encryption_public_key.as_ref()
+        );
+        assert_eq!(
+            Some(node_pks.idkg_dealing_encryption_key()),
+            keys_before.idkg_dealing_encryption_public_key.as_ref()
+        );
+        assert_eq!(
+            Some(node_pks.committee_signing_key()),
+            csp.current_node_public_keys()
+                .expect("error retrieving public keys")
+                .committee_signing_public_key
+                .as_ref()
+        );
+    })
+}
+
+#[test]
+fn should_generate_only_missing_committee_signing_key_with_remote_csp_vault() {
+    let tokio_rt = new_tokio_runtime();
+    let (temp_dir, socket_path) = start_new_remote_csp_vault_server_in_temp_dir(tokio_rt.handle());
+    let config =
+        CryptoConfig::new_with_unix_socket_vault(temp_dir.path().to_path_buf(), socket_path);
+    let csp = Csp::new(
+        &config,
+        Some(tokio_rt.handle().clone()),
+        None,
+        Arc::new(CryptoMetrics::none()),
+    );
+    let node_signing_public_key = generate_node_signing_keys(&csp);
+    let node_id = derive_node_id(&node_signing_public_key).expect("invalid node signing key");
+    let _tls_certificate = generate_tls_keys(&csp, node_id);
+    let _dkg_dealing_encryption_public_key = generate_dkg_dealing_encryption_keys(&csp, node_id);
+    let _idkg_dealing_encryption_public_key =
+        generate_idkg_dealing_encryption_keys(&csp).expect("error generating iDKG keys");
+
+    let node_pks = ensure_all_keys(&config, Some(tokio_rt.handle().clone()), node_id)
+        .expect("error ensuring keys");
+
+    assert_eq!(node_pks.node_id(), node_id);
+    assert_eq!(
+        Some(node_pks.committee_signing_key()),
+        csp.current_node_public_keys()
+            .expect("error retrieving public keys")
+            .committee_signing_public_key
+            .as_ref()
+    );
+}
+
+#[test]
+fn should_not_generate_keys_when_ensuring_keys_of_fully_provisioned_node() {
+    CryptoConfig::run_with_temp_config(|config| {
+        let node_pks =
+            generate_node_keys_once(&config, None).expect("error generating node public keys");
+
+        let ensured_node_pks =
+            ensure_all_keys(&config, None, node_pks.node_id()).expect("error ensuring keys");
+
+        assert_eq!(ensured_node_pks, node_pks);
+    })
+}
+
+#[test]
+fn should_fail_to_ensure_keys_for_wrong_node_id() {
+    CryptoConfig::run_with_temp_config(|config| {
+        let _node_pks =
+            generate_node_keys_once(&config, None).expect("error generating node public keys");
+
+        let result = ensure_all_keys(&config, None, node_test_id(42));
+
+        assert_matches!(result, Err(CryptoError::InvalidArgument { .. }));
+    })
+}
+
+#[test]
+fn should_fail_to_ensure_keys_without_node_signing_key() {
+    CryptoConfig::run_with_temp_config(|config| {
+        let result = ensure_all_keys(&config, None, node_test_id(42));
+
+        assert_matches!(result, Err(CryptoError::InvalidArgument { .. }));
+    })
+}
+
+#[test]
+fn should_wipe_all_secret_keys() {
+    CryptoConfig::run_with_temp_config(|config| {
+        let node_pks =
+            generate_node_keys_once(&config, None).expect("error generating node public keys");
+
+        let removed_keys_count = wipe_secret_keys(&config).expect("error wiping secret keys");
+
+        assert_eq!(removed_keys_count, 5);
+        let csp = Csp::new(&config, None, None, Arc::new(CryptoMetrics::none()));
+        assert!(csp.validate_pks_and_sks().is_err());
+        assert_eq!(
+            csp.current_node_public_keys()
+                .expect("error retrieving public keys")
+                .node_signing_public_key
+                .as_ref(),
+            Some(node_pks.node_signing_key())
+        );
+    })
+}
+
+#[test]
+fn should_wipe_no_secret_keys_twice() {
+    CryptoConfig::run_with_temp_config(|config| {
+        let _node_pks =
+            generate_node_keys_once(&config, None).expect("error generating node public keys");
+        let _removed_keys_count = wipe_secret_keys(&config).expect("error wiping secret keys");
+
+        let removed_keys_count = wipe_secret_keys(&config).expect("error wiping secret keys");
+
+        assert_eq!(removed_keys_count, 0);
+    })
+}
+
+#[test]
+fn should_refuse_to_wipe_secret_keys_of_remote_vault() {
+    let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+    let config = CryptoConfig::new_with_unix_socket_vault(
+        temp_dir.path().to_path_buf(),
+        temp_dir.path().join("vault.sock"),
+    );
+
+    let result = wipe_secret_keys(&config);
+
+    assert_matches!(result, Err(CryptoError::InvalidArgument { .. }));
+}
+
+#[test]
+fn should_verify_created_key_attestation() {
+    CryptoConfig::run_with_temp_config(|config| {
+        let node_pks =
+            generate_node_keys_once(&config, None).expect("error generating node public keys");
+
+        let attestation = create_key_attestation(&config, None, b"rack 7, slot 3")
+            .expect("error creating attestation");
+
+        assert_eq!(attestation.node_id, node_pks.node_id());
+        assert_eq!(attestation.operator_payload, b"rack 7, slot 3".to_vec());
+        assert_eq!(verify_key_attestation(&attestation, &node_pks), Ok(()));
+    })
+}
+
+#[test]
+fn should_verify_key_attestation_created_with_remote_csp_vault() {
+    let tokio_rt = new_tokio_runtime();
+    let (temp_dir, socket_path) = start_new_remote_csp_vault_server_in_temp_dir(tokio_rt.handle());
+    let config =
+        CryptoConfig::new_with_unix_socket_vault(temp_dir.path().to_path_buf(), socket_path);
+    let node_pks = generate_node_keys_once(&config, Some(tokio_rt.handle().clone()))
+        .expect("error generating node public keys");
+
+    let attestation =
+        create_key_attestation(&config, Some(tokio_rt.handle().clone()), b"rack 7, slot 3")
+            .expect("error creating attestation");
+
+    assert_eq!(verify_key_attestation(&attestation, &node_pks), Ok(()));
+}
+
+#[test]
+fn should_return_invalid_argument_when_creating_key_attestation_in_missing_crypto_root() {
+    let temp_dir = tempfile::TempDir::new().expect("failed to create temp dir");
+    let config = CryptoConfig::new(temp_dir.path().join("missing"));
+
+    let result = create_key_attestation(&config, None, b"rack 7, slot 3");
+
+    assert_matches!(result, Err(CryptoError::InvalidArgument { .. }));
+}
+
+#[test]
+fn should_fail_to_verify_key_attestation_with_tampered_fingerprint() {
+    CryptoConfig::run_with_temp_config(|config| {
+        let node_pks =
+            generate_node_keys_once(&config, None).expect("error generating node public keys");
+        let mut attestation = create_key_attestation(&config, None, b"rack 7, slot 3")
+            .expect("error creating attestation");
+
+        attestation.idkg_dealing_encryption_key_fingerprint[0] ^= 1;
+
+        assert_matches!(
+            verify_key_attestation(&attestation, &node_pks),
+            Err(CryptoError::InvalidArgument { .. })
+        );
+    })
+}
+
+#[test]
+fn should_fail_to_verify_key_attestation_with_tampered_payload() {
+    CryptoConfig::run_with_temp_config(|config| {
+        let node_pks =
+            generate_node_keys_once(&config, None).expect("error generating node public keys");
+        let mut attestation = create_key_attestation(&config, None, b"rack 7, slot 3")
+            .expect("error creating attestation");
+
+        attestation.operator_payload = b"rack 7, slot 4".to_vec();
+
+        assert_matches!(
+            verify_key_attestation(&attestation, &node_pks),
+            Err(CryptoError::SignatureVerification { .. })
+        );
+    })
+}
+
+#[test]
+fn should_fail_to_verify_key_attestation_against_other_node_public_keys() {
+    CryptoConfig::run_with_temp_config(|config| {
+        let _node_pks =
+            generate_node_keys_once(&config, None).expect("error generating node public keys");
+        let attestation = create_key_attestation(&config, None, b"rack 7, slot 3")
+            .expect("error creating attestation");
+        CryptoConfig::run_with_temp_config(|other_config| {
+            let other_node_pks = generate_node_keys_once(&other_config, None)
+                .expect("error generating node public keys");
+
+            assert_matches!(
+                verify_key_attestation(&attestation, &other_node_pks),
+                Err(CryptoError::InvalidArgument { .. })
+            );
+        })
+    })
+}
+
+fn registry_keys(node_pks: &ValidNodePublicKeys) -> NodePublicKeys {
+    NodePublicKeys {
+        version: 1,
+        node_signing_pk: Some(node_pks.node_signing_key().clone()),
+        committee_signing_pk: Some(node_pks.committee_signing_key().clone()),
+        tls_certificate: Some(node_pks.tls_certificate().clone()),
+        dkg_dealing_encryption_pk: Some(node_pks.dkg_dealing_encryption_key().clone()),
+        idkg_dealing_encryption_pks: vec![node_pks.idkg_dealing_encryption_key().clone()],
+    }
+}
+
+fn time(date_time: DateTime<Utc>) -> Time {
+    Time::from_nanos_since_unix_epoch(date_time.timestamp() as u64 * 1_000_000_000)
+}
+
+fn utc_date_time(rfc3339: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(rfc3339)
+        .expect("invalid RFC 3339 date")
+        .with_timezone(&Utc)
+}
+
+fn file_size(path: &std::path::Path) -> usize {
+    std::fs::metadata(path)
+        .unwrap_or_else(|e| panic!("failed to read metadata of {:?}: {}", path, e))
+        .len() as usize
+}
+
+// Returns the sizes of the public and the secret key store, which are 0 if the
+// respective store has not been written yet.
+fn key_store_sizes(config: &CryptoConfig) -> (usize, usize) {
+    let size_if_exists = |file_name: &str| {
+        let path = config.crypto_root.join(file_name);
+        if path.exists() {
+            file_size(&path)
+        } else {
+            0
+        }
+    };
+    (
+        size_if_exists("public_keys.pb"),
+        size_if_exists("sks_data.pb"),
+    )
+}
+
+fn assert_within_tight_band(key_type: &str, actual: usize, estimated: usize) {
+    assert!(
+        actual >= estimated - estimated / 4 && actual <= estimated + estimated / 4,
+        "actual size {} of the {} key material is not within the band around the estimated size {}",
+        actual,
+        key_type,
+        estimated
+    );
+}
+
+fn assert_within_band(actual: usize, estimated: usize) {
+    assert!(
+        actual >= estimated / 2 && actual <= estimated + estimated / 2,
+        "actual size {} is not within the band around the estimated size {}",
+        actual,
+        estimated
+    );
+}
+
 fn local_crypto_component(config: &CryptoConfig) -> Arc<CryptoComponentImpl<Csp>> {
     crypto_component(config, None)
 }
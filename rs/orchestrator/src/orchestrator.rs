@@ -86,6 +86,17 @@ impl Orchestrator {
                     NodeKeyGenerationError::TransientInternalError(e) => {
                         OrchestratorInstantiationError::KeyGenerationError(e)
                     }
+                    NodeKeyGenerationError::Locked(e) => {
+                        OrchestratorInstantiationError::KeyGenerationError(e)
+                    }
+                    NodeKeyGenerationError::CryptoRootNotFound { .. }
+                    | NodeKeyGenerationError::CryptoRootNotADirectory { .. }
+                    | NodeKeyGenerationError::CryptoRootIsSymlink { .. }
+                    | NodeKeyGenerationError::StoreFailed { .. }
+                    | NodeKeyGenerationError::TlsKeyMaterialOnly
+                    | NodeKeyGenerationError::CryptoRootLockFailed { .. } => {
+                        OrchestratorInstantiationError::KeyGenerationError(format!("{:?}", e))
+                    }
                 })
         })
         .await